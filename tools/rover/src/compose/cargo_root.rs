@@ -1,48 +1,204 @@
-use super::config::Config;
+use super::{config::Config, file::FileProcessor};
 use anyhow::{Context, Result};
-use std::{
-    fs,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, Document, Value};
 
-pub fn cargo_root(output: &Path, config: &Config) -> Result<()> {
-    let problems: Vec<_> = config
+const MANIFEST: &str = "Cargo.toml";
+
+/// The workspace members of the public repository: composed problems, tools and `add-to-toml`.
+fn public_members(problems_folder: &Path, config: &Config) -> Vec<String> {
+    let problems = config
         .get_problems()
         .iter()
-        .map(|path| {
-            let path = PathBuf::from("problems").join(path);
-            path.to_str().unwrap().to_string()
-        })
-        .collect();
-    let tools: Vec<_> = config
+        .map(|path| problems_folder.join(path));
+    let tools = config
         .get_tools()
         .iter()
-        .map(|path| {
-            let path = PathBuf::from("tools").join(path);
-            path.to_str().unwrap().to_string()
-        })
-        .collect();
-    let add_to_toml: Vec<_> = config
-        .get_add_to_toml()
-        .iter()
+        .map(|path| PathBuf::from("tools").join(path));
+    problems
+        .chain(tools)
+        .chain(config.get_add_to_toml().iter().cloned())
         .map(|path| path.to_str().unwrap().to_string())
-        .collect();
-    let content = format!(
-        r#"[workspace]
+        .collect()
+}
+
+fn has_comment(value: &Value) -> bool {
+    value
+        .decor()
+        .prefix()
+        .and_then(|prefix| prefix.as_str())
+        .is_some_and(|prefix| prefix.contains('#'))
+}
+
+/// Keeps the listed members in their order, moving the comments of the removed ones
+/// to the next kept member, and appends the missing ones.
+fn filter_members(array: &mut Array, members: &[String]) {
+    let mut kept: Vec<Value> = Vec::new();
+    let mut orphaned_prefix = None;
+    for value in array.iter() {
+        let mut value = value.clone();
+        let is_member = value
+            .as_str()
+            .is_some_and(|member| members.iter().any(|m| m == member));
+        if !is_member {
+            if has_comment(&value) && orphaned_prefix.is_none() {
+                orphaned_prefix = value.decor().prefix().cloned();
+            }
+            continue;
+        }
+        if let Some(prefix) = orphaned_prefix.take() {
+            if !has_comment(&value) {
+                value.decor_mut().set_prefix(prefix);
+            }
+        }
+        kept.push(value);
+    }
+    let indent = kept
+        .last()
+        .and_then(|value| value.decor().prefix())
+        .and_then(|prefix| prefix.as_str())
+        .and_then(|prefix| prefix.rsplit('\n').next())
+        .unwrap_or("    ")
+        .to_string();
+    for member in members {
+        if !kept.iter().any(|value| value.as_str() == Some(member)) {
+            let mut value = Value::from(member.as_str());
+            value.decor_mut().set_prefix(format!("\n{indent}"));
+            kept.push(value);
+        }
+    }
+    array.clear();
+    for value in kept {
+        array.push_formatted(value);
+    }
+}
+
+/// Derives the public root manifest from the private one, leaving everything but
+/// the workspace members as is.
+fn public_manifest(private: &str, members: &[String]) -> Result<String> {
+    let mut manifest: Document = private.parse()?;
+    let workspace = manifest["workspace"]
+        .as_table_like_mut()
+        .context("no [workspace] table")?;
+    let array = workspace
+        .get_mut("members")
+        .and_then(|item| item.as_array_mut())
+        .context("no workspace members list")?;
+    filter_members(array, members);
+    if let Some(array) = workspace
+        .get_mut("default-members")
+        .and_then(|item| item.as_array_mut())
+    {
+        array.retain(|value| {
+            value
+                .as_str()
+                .is_some_and(|member| members.iter().any(|m| m == member))
+        });
+    }
+    Ok(manifest.to_string())
+}
+
+/// Composes the root manifest like the other files, then keeps only the public members.
+pub fn cargo_root(
+    input: &Path,
+    problems_folder: &Path,
+    processor: &mut FileProcessor,
+    config: &Config,
+) -> Result<()> {
+    let path = input.join(MANIFEST);
+    let output = Path::new(MANIFEST);
+    processor.process_file(&path, output)?;
+    let composed = String::from_utf8(processor.files()[output].read()?.into_owned())
+        .with_context(|| format!("composed {path:?} is not UTF-8"))?;
+    let content = public_manifest(&composed, &public_members(problems_folder, config))
+        .with_context(|| format!("failed to compose {path:?}"))?;
+    processor.replace_file(output, content);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_everything_but_private_members() {
+        let private = r#"[workspace]
+resolver = "2"
 members = [
     # Problems
-    "{}",
+    "problems/tutorial/secret",
+    "problems/tutorial/add",
+    "problems/intro/conway",
 
     # Tools
-    "{}",
+    "tools/grader",
+    "tools/rover",
+]
+
+[workspace.dependencies]
+anyhow = "1" # errors
 
-    # Additional
-    "{}",
+[profile.release]
+debug = true
+"#;
+        let members = [
+            "problems/tutorial/add".to_string(),
+            "tools/rover".to_string(),
+            "extra/crate".to_string(),
+        ];
+        let expected = r#"[workspace]
+resolver = "2"
+members = [
+    # Problems
+    "problems/tutorial/add",
+
+    # Tools
+    "tools/rover",
+    "extra/crate",
 ]
+
+[workspace.dependencies]
+anyhow = "1" # errors
+
+[profile.release]
+debug = true
+"#;
+        assert_eq!(public_manifest(private, &members).unwrap(), expected);
+    }
+
+    #[test]
+    fn removes_private_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(MANIFEST),
+            r#"[workspace]
+members = [
+    "problems/tutorial/add",
+    "problems/tutorial/secret",
+]
+
+# compose::begin_private(no_hint)
+[patch.crates-io]
+grader = { path = "../private-grader" }
+# compose::end_private
+
+[profile.release]
+debug = true # compose::private(no_hint)
 "#,
-        problems.join("\",\n    \""),
-        tools.join("\",\n    \""),
-        add_to_toml.join("\",\n    \"")
-    );
-    fs::write(output.join("Cargo.toml"), content).context("failed to write Cargo.toml")
+        )
+        .unwrap();
+        let config: Config = serde_yaml::from_str(
+            "problems: [tutorial/add]\ntools: []\ncopy: []\nskip-entries: []\n\
+             add-to-toml: []\ndo-not-delete: []\n",
+        )
+        .unwrap();
+        let mut processor = FileProcessor::new(Default::default());
+        cargo_root(dir.path(), Path::new("problems"), &mut processor, &config).unwrap();
+        let composed = processor.files()[Path::new(MANIFEST)].read().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&composed).unwrap(),
+            "[workspace]\nmembers = [\n    \"problems/tutorial/add\",\n]\n\n[profile.release]\n"
+        );
+        assert!(processor.source_maps().is_empty());
+    }
 }
@@ -0,0 +1,67 @@
+use super::file::SourceMap;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+};
+
+/// Runs `cargo check` on the composed workspace, blaming the markers for the errors.
+/// The source maps are by the paths relative to the workspace.
+pub fn check_workspace(
+    output: &Path,
+    target_dir: &Path,
+    source_maps: &BTreeMap<PathBuf, SourceMap>,
+) -> Result<()> {
+    let result = process::Command::new("cargo")
+        .args([
+            "check",
+            "--workspace",
+            "--all-targets",
+            "--message-format",
+            "json",
+        ])
+        .current_dir(output)
+        .env("CARGO_TARGET_DIR", target_dir)
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to launch cargo check")?;
+    let mut errors = 0;
+    for line in String::from_utf8_lossy(&result.stdout).lines() {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] != "compiler-message" || message["message"]["level"] != "error" {
+            continue;
+        }
+        errors += 1;
+        let message = &message["message"];
+        eprintln!("error: {}", message["message"].as_str().unwrap_or_default());
+        let span = message["spans"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|span| span["is_primary"] == true);
+        let span = match span {
+            Some(span) => span,
+            None => continue,
+        };
+        let file = Path::new(span["file_name"].as_str().unwrap_or_default());
+        let file = file.strip_prefix(output).unwrap_or(file);
+        let line = span["line_start"].as_u64().unwrap_or_default() as usize;
+        eprintln!("  --> {}:{line}", output.join(file).display());
+        if let Some(source_map) = source_maps.get(file) {
+            eprintln!("  {}", source_map.blame(line));
+        }
+    }
+    if errors > 0 {
+        bail!("composed workspace has {errors} compilation errors")
+    }
+    if !result.status.success() {
+        bail!("cargo check of the composed workspace failed")
+    }
+    println!("Composed workspace type-checks");
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use anyhow::{bail, Context, Result};
+use glob::Pattern;
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -14,17 +15,17 @@ pub struct Config {
     skip_entries: Vec<PathBuf>,
     add_to_toml: Vec<PathBuf>,
     do_not_delete: Vec<PathBuf>,
+    #[serde(default)]
+    features: Vec<String>,
 }
 
 impl Config {
     pub fn from_yml(path: &Path) -> Result<Self> {
         let file = File::open(path).context("no yml file with config")?;
         let config: Config = serde_yaml::from_reader(file).context("cannot read file from yml")?;
-        for entry in config.get_do_not_delete() {
-            if entry.iter().count() != 1 {
-                bail!("compose doesn't support do-not-delete on paths, only directories or files: {entry:?}")
-            }
-        }
+        config
+            .do_not_delete_patterns()
+            .context("invalid do-not-delete")?;
         Ok(config)
     }
 
@@ -48,7 +49,26 @@ impl Config {
         self.skip_entries.as_slice()
     }
 
-    pub fn get_do_not_delete(&self) -> &[PathBuf] {
-        self.do_not_delete.as_slice()
+    /// Glob patterns of the paths in the output repository which compose must not delete.
+    pub fn do_not_delete_patterns(&self) -> Result<Vec<Pattern>> {
+        let mut patterns = Vec::new();
+        for entry in &self.do_not_delete {
+            if entry.is_absolute()
+                || entry
+                    .components()
+                    .any(|component| matches!(component, Component::ParentDir))
+            {
+                bail!("{entry:?} is not a path within the output repository")
+            }
+            let pattern = entry.to_str().context("non-utf-8 path")?;
+            patterns.push(
+                Pattern::new(pattern).with_context(|| format!("{entry:?} is not a valid glob"))?,
+            );
+        }
+        Ok(patterns)
+    }
+
+    pub fn get_features(&self) -> &[String] {
+        self.features.as_slice()
     }
 }
@@ -1,8 +1,8 @@
-use super::file::process_file;
+use super::file::FileProcessor;
 use anyhow::{Context, Result};
 use std::{fs, path::Path};
 
-pub fn process_dir(input: &Path, output: &Path) -> Result<()> {
+pub fn process_dir(input: &Path, output: &Path, processor: &mut FileProcessor) -> Result<()> {
     let dir = fs::read_dir(input).with_context(|| format!("failed to read directory {input:?}"))?;
     for entry in dir {
         let input = entry
@@ -10,9 +10,9 @@ pub fn process_dir(input: &Path, output: &Path) -> Result<()> {
             .path();
         let output = output.join(input.file_name().unwrap());
         if input.is_dir() {
-            process_dir(&input, &output)?;
+            process_dir(&input, &output, processor)?;
         } else {
-            process_file(&input, &output)?;
+            processor.process_file(&input, &output)?;
         }
     }
     Ok(())
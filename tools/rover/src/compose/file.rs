@@ -1,168 +1,784 @@
+//! Markers in the private sources which control what gets into the public repository.
+//!
+//! A marker is a comment `// compose::COMMAND` or `// compose::COMMAND(PROPERTIES)`,
+//! written with the comment syntax of the file, e.g. `# compose::private` in TOML or
+//! `<!-- compose::begin_private -->` in Markdown, see [`super::language`]:
+//!
+//! * `private` drops the line it is on;
+//! * `begin_private` ... `end_private` drops the lines between them;
+//! * `begin_public` ... `end_public` keeps the lines between them only in the public
+//!   repository, removing one level of comments from them, so they stay commented out here.
+//!
+//! Dropped code is replaced with a hint and, optionally, a stub. The properties are:
+//!
+//! * `no_hint` drops the code without a trace;
+//! * `hint("text")` replaces the default "TODO: your code goes here." hint;
+//! * `unimplemented` puts `unimplemented!()` after the hint, or the similar stub of the
+//!   language;
+//! * `replace_with("code")` puts the given code after the hint, `\n` splits it into lines;
+//! * `if("feature")` or `if("!feature")` applies the marker only when the feature is
+//!   enabled or disabled respectively, otherwise private code is kept and public code is
+//!   dropped.
+
+use super::language::{language_of, Comment, Language};
 use anyhow::{bail, Context, Result};
-use std::{fs, path::Path};
+use proc_macro2::LineColumn;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    iter::Peekable,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+const MARKER: &str = "compose::";
+const DEFAULT_HINT: &str = "TODO: your code goes here.";
 
-#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum TokenKind {
     Private,
     BeginPrivate,
     EndPrivate,
+    BeginPublic,
+    EndPublic,
+}
+
+impl TokenKind {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "private" => Self::Private,
+            "begin_private" => Self::BeginPrivate,
+            "end_private" => Self::EndPrivate,
+            "begin_public" => Self::BeginPublic,
+            "end_public" => Self::EndPublic,
+            name => bail!("unknown compose command: {name}"),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Private => "private",
+            Self::BeginPrivate => "begin_private",
+            Self::EndPrivate => "end_private",
+            Self::BeginPublic => "begin_public",
+            Self::EndPublic => "end_public",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Condition {
+    feature: String,
+    negated: bool,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TokenProperty {
-    NoHint,
-    Unimplemented,
+impl Condition {
+    fn parse(text: &str) -> Result<Self> {
+        let (feature, negated) = match text.strip_prefix('!') {
+            Some(feature) => (feature, true),
+            None => (text, false),
+        };
+        if feature.is_empty() {
+            bail!("empty feature name in condition");
+        }
+        Ok(Self {
+            feature: feature.to_string(),
+            negated,
+        })
+    }
+
+    fn holds(&self, features: &BTreeSet<String>) -> bool {
+        features.contains(&self.feature) != self.negated
+    }
 }
 
+#[derive(Debug)]
 struct Token {
     kind: TokenKind,
-    properties: Vec<TokenProperty>,
+    no_hint: bool,
+    hint: Option<String>,
+    replacement: Option<String>,
+    condition: Option<Condition>,
 }
 
-fn parse_token(line: &str) -> Result<Option<Token>> {
-    let comment = match line.find("//") {
-        Some(pos) => &line[pos..],
-        None => return Ok(None),
-    };
+impl Token {
+    fn is_active(&self, features: &BTreeSet<String>) -> bool {
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.holds(features))
+    }
+
+    /// The lines replacing the private code in the public repository.
+    fn stub(&self, comment: &Comment) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.no_hint {
+            let hint = self.hint.as_deref().unwrap_or(DEFAULT_HINT);
+            lines.extend(hint.lines().map(|line| comment.wrap(line)));
+        }
+        if let Some(replacement) = &self.replacement {
+            lines.extend(replacement.lines().map(String::from));
+        }
+        lines
+    }
+}
+
+/// Parses `name`, `name("string")` items separated by commas.
+struct PropertyParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> PropertyParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("expected '{expected}', found '{c}'"),
+            None => bail!("expected '{expected}', found end of marker"),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        let mut identifier = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            identifier.push(c);
+        }
+        if identifier.is_empty() {
+            match self.chars.peek() {
+                Some(c) => bail!("expected property name, found '{c}'"),
+                None => bail!("expected property name, found end of marker"),
+            }
+        }
+        Ok(identifier)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some(c @ ('"' | '\\')) => string.push(c),
+                    Some(c) => bail!("unknown escape sequence '\\{c}'"),
+                    None => bail!("unterminated string"),
+                },
+                Some(c) => string.push(c),
+                None => bail!("unterminated string"),
+            }
+        }
+    }
+
+    fn properties(mut self) -> Result<Vec<(String, Option<String>)>> {
+        let mut properties = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek().is_none() {
+                return Ok(properties);
+            }
+            let name = self.identifier()?;
+            self.skip_whitespace();
+            let argument = if self.chars.next_if_eq(&'(').is_some() {
+                self.skip_whitespace();
+                let argument = self.string()?;
+                self.skip_whitespace();
+                self.expect(')')?;
+                self.skip_whitespace();
+                Some(argument)
+            } else {
+                None
+            };
+            properties.push((name, argument));
+            if self.chars.peek().is_some() {
+                self.expect(',')?;
+            }
+        }
+    }
+}
 
-    let cmd = match comment.find("compose::") {
-        Some(pos) => &comment[pos + "compose::".len()..],
+/// Finds the byte offset of the comment with the marker in the line and the marker command.
+fn find_marker<'a>(line: &'a str, comment: &Comment) -> Option<(usize, &'a str)> {
+    let pos = line.find(MARKER)?;
+    let start = line[..pos].rfind(comment.start)?;
+    if !line[start + comment.start.len()..pos].trim().is_empty() {
+        return None;
+    }
+    let cmd = line[pos + MARKER.len()..].trim_end();
+    let cmd = cmd.strip_suffix(comment.end).unwrap_or(cmd).trim_end();
+    Some((start, cmd))
+}
+
+fn parse_token(line: &str, language: &Language) -> Result<Option<Token>> {
+    let cmd = match find_marker(line, &language.comment) {
+        Some((_, cmd)) => cmd,
         None => return Ok(None),
     };
 
-    let kind = if cmd.starts_with("private") {
-        TokenKind::Private
-    } else if cmd.starts_with("begin_private") {
-        TokenKind::BeginPrivate
-    } else if cmd.starts_with("end_private") {
-        TokenKind::EndPrivate
+    let name_len = cmd
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(cmd.len());
+    let kind = TokenKind::from_name(&cmd[..name_len])?;
+    let rest = cmd[name_len..].trim_start();
+    let properties = if rest.is_empty() {
+        vec![]
+    } else if let Some(inner) = rest.strip_prefix('(') {
+        let inner = inner.strip_suffix(')').context("unclosed '('")?;
+        PropertyParser::new(inner).properties()?
     } else {
-        bail!("unknown compose command: {}", cmd);
+        bail!("unexpected text after '{}': {rest}", kind.name());
     };
 
-    let properties_str = match cmd.find('(') {
-        Some(pos) => {
-            if !cmd.trim_end().ends_with(')') {
-                bail!("unclosed '('");
+    let mut token = Token {
+        kind,
+        no_hint: false,
+        hint: None,
+        replacement: None,
+        condition: None,
+    };
+    for (name, argument) in properties {
+        match (name.as_str(), argument) {
+            ("no_hint", None) => token.no_hint = true,
+            ("unimplemented", None) => match language.unimplemented {
+                Some(stub) => token.replacement = Some(stub.to_string()),
+                None => bail!(
+                    "property unimplemented is not supported in {}",
+                    language.name
+                ),
+            },
+            ("hint", Some(hint)) => token.hint = Some(hint),
+            ("replace_with", Some(code)) => token.replacement = Some(code),
+            ("if", Some(condition)) => token.condition = Some(Condition::parse(&condition)?),
+            ("no_hint" | "unimplemented", Some(_)) => {
+                bail!("property {name} takes no argument")
+            }
+            ("hint" | "replace_with" | "if", None) => {
+                bail!("property {name} requires a string argument")
             }
-            &cmd[pos + 1..cmd.trim_end().len() - 1]
+            (name, _) => bail!("unknown property: {name}"),
         }
-        None => "",
+        let allowed = match kind {
+            TokenKind::Private | TokenKind::BeginPrivate => true,
+            TokenKind::BeginPublic => name == "if",
+            TokenKind::EndPrivate | TokenKind::EndPublic => false,
+        };
+        if !allowed {
+            bail!("property {name} is not allowed on '{}'", kind.name());
+        }
+    }
+    if token.no_hint && token.hint.is_some() {
+        bail!("properties no_hint and hint are mutually exclusive");
+    }
+    Ok(Some(token))
+}
+
+/// Removes the marker comment from the line, dropping the line if nothing else is left.
+fn strip_marker<'a>(line: &'a str, comment: &Comment) -> Option<&'a str> {
+    let code = match find_marker(line, comment) {
+        Some((comment, _)) => line[..comment].trim_end(),
+        None => line,
     };
+    (!code.trim().is_empty()).then_some(code)
+}
+
+/// Removes one level of comment from the line of a public block.
+fn uncomment(line: &str, comment: &Comment) -> String {
+    match comment.unwrap(line) {
+        Some(code) => format!("{}{code}", indentation(line)),
+        None => line.to_string(),
+    }
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+struct Block {
+    line: usize,
+    token: Token,
+    active: bool,
+}
+
+/// The part of a composed file produced by a marker, used to point at the marker
+/// when the composed file turns out to be broken.
+#[derive(Debug)]
+pub struct Replacement {
+    marker: &'static str,
+    /// 1-based line of the marker in the private source.
+    source_line: usize,
+    /// 1-based lines of the composed file, empty if the marker produced nothing.
+    output_lines: Range<usize>,
+}
+
+/// The markers which changed a composed file.
+#[derive(Debug)]
+pub struct SourceMap {
+    source: PathBuf,
+    replacements: Vec<Replacement>,
+}
 
-    let mut properties = vec![];
-    for prop in properties_str.split_inclusive(',') {
-        match prop {
-            "no_hint" => properties.push(TokenProperty::NoHint),
-            "unimplemented" => properties.push(TokenProperty::Unimplemented),
-            s => bail!("unknown property: {}", s),
+impl SourceMap {
+    /// Describes the marker which most likely produced the error on the line of the composed file.
+    pub fn blame(&self, line: usize) -> String {
+        let replacement = self
+            .replacements
+            .iter()
+            .rev()
+            .find(|replacement| replacement.output_lines.start <= line);
+        match replacement {
+            Some(replacement) => format!(
+                "produced by '{}' on line {} of {}",
+                replacement.marker,
+                replacement.source_line,
+                self.source.display()
+            ),
+            None => format!("no marker of {} precedes it", self.source.display()),
         }
     }
+}
 
-    Ok(Some(Token { kind, properties }))
+#[derive(Default)]
+struct Output {
+    text: String,
+    lines: usize,
+    replacements: Vec<Replacement>,
 }
 
-fn find_token(lines: &[&str], start: usize) -> Result<Option<(usize, Token)>> {
-    for (i, line) in lines[start..].iter().enumerate() {
-        let mb_token = parse_token(line)
-            .with_context(|| format!("failed to parse token on line {}", i + 1))?;
-        if let Some(token) = mb_token {
-            return Ok(Some((i + start, token)));
+impl Output {
+    fn push_line(&mut self, line: &str) {
+        self.text += line;
+        self.text += "\n";
+        self.lines += 1;
+    }
+
+    fn push_stub(&mut self, lines: &[&str], line: usize, token: &Token, comment: &Comment) {
+        let start = self.lines + 1;
+        let indent = indentation(lines[line]);
+        for stub_line in token.stub(comment) {
+            self.push_line(&format!("{indent}{stub_line}"));
         }
+        self.replace(token.kind, line, start);
+    }
+
+    fn replace(&mut self, kind: TokenKind, line: usize, start: usize) {
+        self.replacements.push(Replacement {
+            marker: kind.name(),
+            source_line: line + 1,
+            output_lines: start..self.lines + 1,
+        });
     }
-    Ok(None)
 }
 
-fn process_source(src: String) -> Result<String> {
-    let mut dst = String::new();
+fn process_source(src: String, features: &BTreeSet<String>, language: &Language) -> Result<Output> {
+    let mut dst = Output::default();
+    let comment = &language.comment;
+
+    let opaque = language
+        .opaque
+        .map(|opaque| opaque(&src))
+        .unwrap_or_default();
+    let is_opaque = |line: usize, column: usize| {
+        let position = LineColumn { line, column };
+        opaque
+            .iter()
+            .any(|(start, end)| *start <= position && position < *end)
+    };
 
     let lines = src.lines().collect::<Vec<_>>();
-    let mut next_pos = 0;
-    while let Some((begin, token)) = find_token(&lines, next_pos)? {
-        let end = match token.kind {
-            TokenKind::EndPrivate => bail!("unpaired 'end_private' on line {}", begin + 1),
-            TokenKind::Private => begin + 1,
-            TokenKind::BeginPrivate => {
-                let mut pos = begin + 1;
-                let mut mb_end: Option<usize> = None;
-                while let Some((k, token)) = find_token(&lines, pos)? {
-                    match token.kind {
-                        TokenKind::BeginPrivate => {
-                            bail!("nested 'begin_private' on line {}", k + 1)
-                        }
-                        TokenKind::Private => pos = k + 1,
-                        TokenKind::EndPrivate => {
-                            mb_end = Some(k);
-                            break;
+    let mut block: Option<Block> = None;
+    let mut skip_blank = false;
+    for (i, line) in lines.iter().enumerate() {
+        let token = match find_marker(line, comment) {
+            Some((start, _)) if is_opaque(i + 1, line[..start].chars().count()) => None,
+            _ => parse_token(line, language)
+                .with_context(|| format!("invalid marker on line {}", i + 1))?,
+        };
+        if std::mem::take(&mut skip_blank) && token.is_none() && line.trim().is_empty() {
+            continue;
+        }
+        let open = block.as_ref().map(|block| block.token.kind);
+        let token = match token {
+            Some(token) => token,
+            None => {
+                match &block {
+                    None => dst.push_line(line),
+                    Some(block) if !block.active => {
+                        if block.token.kind == TokenKind::BeginPrivate {
+                            dst.push_line(line);
                         }
                     }
+                    Some(block) if block.token.kind == TokenKind::BeginPublic => {
+                        dst.push_line(&uncomment(line, comment))
+                    }
+                    Some(_) => {}
                 }
-                match mb_end {
-                    Some(end) => end + 1,
-                    None => bail!("unclosed 'begin_private' on line {}", begin + 1),
-                }
+                continue;
             }
         };
-
-        #[allow(clippy::needless_range_loop)]
-        for i in next_pos..begin {
-            dst += lines[i];
-            dst += "\n";
-        }
-
-        let no_hint = token.properties.contains(&TokenProperty::NoHint);
-        let unimpl = token.properties.contains(&TokenProperty::Unimplemented);
-        if no_hint {
-            if begin > 0
-                && lines[begin - 1].trim().is_empty()
-                && end < lines.len()
-                && lines[end].trim().is_empty()
-            {
-                next_pos = end + 1;
-            } else {
-                next_pos = end;
+        match (open, token.kind) {
+            (_, TokenKind::Private) if block.as_ref().is_some_and(|block| block.active) => {
+                if open == Some(TokenKind::BeginPublic) {
+                    bail!("'private' inside 'begin_public' on line {}", i + 1);
+                }
+            }
+            (Some(TokenKind::BeginPublic), TokenKind::Private) => {
+                bail!("'private' inside 'begin_public' on line {}", i + 1)
+            }
+            (_, TokenKind::Private) => {
+                if token.is_active(features) {
+                    dst.push_stub(&lines, i, &token, comment);
+                    skip_blank = token.no_hint
+                        && token.replacement.is_none()
+                        && i > 0
+                        && lines[i - 1].trim().is_empty();
+                } else if let Some(code) = strip_marker(line, comment) {
+                    dst.push_line(code);
+                }
+            }
+            (None, TokenKind::BeginPrivate | TokenKind::BeginPublic) => {
+                let active = token.is_active(features);
+                block = Some(Block {
+                    line: i,
+                    token,
+                    active,
+                });
             }
-        } else {
-            let mut insert_line = |line: &str| {
-                for c in lines[begin].chars() {
-                    if c.is_whitespace() {
-                        dst.push(c);
-                    } else {
-                        break;
+            (Some(open), TokenKind::BeginPrivate | TokenKind::BeginPublic) => bail!(
+                "'{}' on line {} inside '{}' on line {}",
+                token.kind.name(),
+                i + 1,
+                open.name(),
+                block.as_ref().unwrap().line + 1
+            ),
+            (Some(TokenKind::BeginPrivate), TokenKind::EndPrivate)
+            | (Some(TokenKind::BeginPublic), TokenKind::EndPublic) => {
+                let block = block.take().unwrap();
+                if !block.active {
+                    continue;
+                }
+                match block.token.kind {
+                    TokenKind::BeginPrivate => {
+                        dst.push_stub(&lines, block.line, &block.token, comment);
+                        skip_blank = block.token.no_hint
+                            && block.token.replacement.is_none()
+                            && block.line > 0
+                            && lines[block.line - 1].trim().is_empty();
+                    }
+                    kind => {
+                        // The uncommented lines are already in the output
+                        let public_lines = i - block.line - 1;
+                        dst.replace(kind, block.line, dst.lines + 1 - public_lines);
                     }
                 }
-                dst.push_str(line);
-            };
+            }
+            (_, kind) => bail!("unpaired '{}' on line {}", kind.name(), i + 1),
+        }
+    }
+    if let Some(block) = block {
+        bail!(
+            "unclosed '{}' on line {}",
+            block.token.kind.name(),
+            block.line + 1
+        );
+    }
+
+    Ok(dst)
+}
+
+/// Checks that the composed file is still valid, blaming the marker which broke it.
+fn verify_source(
+    input: &Path,
+    language: &Language,
+    original: &str,
+    composed: &str,
+    source_map: &SourceMap,
+) -> Result<()> {
+    let verify = match language.verify {
+        Some(verify) => verify,
+        None => return Ok(()),
+    };
+    let err = match verify(composed) {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+    // Compose is not to blame for the files which are broken on their own
+    if verify(original).is_err() {
+        return Ok(());
+    }
+    let line = err.line;
+    let text = composed
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or_default();
+    bail!(
+        "composed version of {input:?} is not valid {}: {} on line {line}\n  {line} | {}\n  {}",
+        language.name,
+        err.message,
+        text.trim(),
+        source_map.blame(line)
+    )
+}
+
+/// The content of a file of the composed repository.
+pub enum Content {
+    Text(String),
+    /// Copied as-is from the private repository.
+    Copy(PathBuf),
+}
+
+impl Content {
+    pub fn read(&self) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Self::Text(text) => Ok(Cow::Borrowed(text.as_bytes())),
+            Self::Copy(path) => fs::read(path)
+                .map(Cow::Owned)
+                .with_context(|| format!("failed to read file {path:?}")),
+        }
+    }
 
-            insert_line("// TODO: your code goes here.\n");
-            if unimpl {
-                insert_line("unimplemented!()\n");
+    pub fn write(&self, output: &Path) -> Result<()> {
+        let out_dir = output.parent().unwrap();
+        fs::create_dir_all(out_dir).context("failed to create directory")?;
+        match self {
+            Self::Text(text) => {
+                fs::write(output, text).with_context(|| format!("failed to write file {output:?}"))
             }
+            Self::Copy(input) => fs::copy(input, output)
+                .map(|_| ())
+                .with_context(|| format!("failed to copy {:?} to {:?}", input, output)),
+        }
+    }
+}
 
-            next_pos = end;
+/// Composes files one by one, remembering the markers applied to each of them.
+pub struct FileProcessor {
+    features: BTreeSet<String>,
+    files: BTreeMap<PathBuf, Content>,
+    source_maps: BTreeMap<PathBuf, SourceMap>,
+}
+
+impl FileProcessor {
+    pub fn new(features: BTreeSet<String>) -> Self {
+        Self {
+            features,
+            files: BTreeMap::new(),
+            source_maps: BTreeMap::new(),
         }
     }
 
-    for line in &lines[next_pos..] {
-        dst += line;
-        dst += "\n";
+    /// The composed files, by their paths relative to the output repository.
+    pub fn files(&mut self) -> &mut BTreeMap<PathBuf, Content> {
+        &mut self.files
     }
 
-    Ok(dst)
+    /// The markers applied to the composed files, by their paths relative to the output repository.
+    pub fn source_maps(&self) -> &BTreeMap<PathBuf, SourceMap> {
+        &self.source_maps
+    }
+
+    /// Replaces the composed file with its edited version, whose lines don't match the markers.
+    pub fn replace_file(&mut self, output: &Path, text: String) {
+        self.source_maps.remove(output);
+        self.files.insert(output.to_path_buf(), Content::Text(text));
+    }
+
+    pub fn process_file(&mut self, input: &Path, output: &Path) -> Result<()> {
+        let content = match language_of(input) {
+            Some(language) => {
+                let content = fs::read_to_string(input)
+                    .with_context(|| format!("failed to read file {:?}", input))?;
+                let composed = process_source(content.clone(), &self.features, language)
+                    .with_context(|| format!("failed to process file {:?}", input))?;
+                let source_map = SourceMap {
+                    source: input.to_path_buf(),
+                    replacements: composed.replacements,
+                };
+                verify_source(input, language, &content, &composed.text, &source_map)?;
+                self.source_maps.insert(output.to_path_buf(), source_map);
+                Content::Text(composed.text)
+            }
+            None => Content::Copy(input.to_path_buf()),
+        };
+        self.files.insert(output.to_path_buf(), content);
+        Ok(())
+    }
 }
 
-pub fn process_file(input: &Path, output: &Path) -> Result<()> {
-    let out_dir = output.parent().unwrap();
-    fs::create_dir_all(out_dir).context("failed to create directory")?;
-    if input.to_str().map(|s| s.ends_with(".rs")).unwrap_or(false) {
-        let content = fs::read_to_string(input)
-            .with_context(|| format!("failed to read file {:?}", input))?;
-        let new_content = process_source(content)
-            .with_context(|| format!("failed to process file {:?}", input))?;
-        fs::write(output, new_content).with_context(|| format!("failed to write file {:?}", input))
-    } else {
-        fs::copy(input, output)
-            .map(|_| ())
-            .with_context(|| format!("failed to copy {:?} to {:?}", input, output))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compose_file(name: &str, src: &str, features: &[&str]) -> Result<String> {
+        let features = features.iter().map(|f| f.to_string()).collect();
+        let language = language_of(Path::new(name)).unwrap();
+        process_source(src.to_string(), &features, language).map(|output| output.text)
+    }
+
+    fn compose(src: &str, features: &[&str]) -> Result<String> {
+        compose_file("lib.rs", src, features)
+    }
+
+    #[test]
+    fn private_blocks() {
+        let src = "\
+fn f() -> i32 {
+    // compose::begin_private(unimplemented)
+    42
+    // compose::end_private
+}
+
+fn g() {
+    secret(); // compose::private(hint(\"call the secret\"), replace_with(\"todo!()\"))
+}
+";
+        let expected = "\
+fn f() -> i32 {
+    // TODO: your code goes here.
+    unimplemented!()
+}
+
+fn g() {
+    // call the secret
+    todo!()
+}
+";
+        assert_eq!(compose(src, &[]).unwrap(), expected);
+    }
+
+    #[test]
+    fn no_hint_removes_extra_blank_line() {
+        let src = "use a;\n\n// compose::begin_private(no_hint)\nfn helper() {}\n// compose::end_private\n\nfn main() {}\n";
+        assert_eq!(compose(src, &[]).unwrap(), "use a;\n\nfn main() {}\n");
+    }
+
+    #[test]
+    fn public_blocks_and_conditions() {
+        let src = "\
+fn f() {
+    // compose::begin_private(if(\"hard\"))
+    solve();
+    // compose::end_private
+    // compose::begin_public(if(\"!hard\"))
+    // helper();
+    // compose::end_public
+}
+";
+        let easy = "\
+fn f() {
+    solve();
+    helper();
+}
+";
+        let hard = "\
+fn f() {
+    // TODO: your code goes here.
+}
+";
+        assert_eq!(compose(src, &[]).unwrap(), easy);
+        assert_eq!(compose(src, &["hard"]).unwrap(), hard);
+    }
+
+    #[test]
+    fn misplaced_marker_is_blamed() {
+        let src = "\
+fn f(x: bool) {
+    // compose::begin_private
+    if x {
+    // compose::end_private
+        g();
+    }
+}
+";
+        let rust = language_of(Path::new("lib.rs")).unwrap();
+        let composed = process_source(src.to_string(), &BTreeSet::new(), rust).unwrap();
+        let source_map = SourceMap {
+            source: PathBuf::from("lib.rs"),
+            replacements: composed.replacements,
+        };
+        let err = verify_source(Path::new("lib.rs"), rust, src, &composed.text, &source_map)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("produced by 'begin_private' on line 2 of lib.rs"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn other_languages() {
+        let manifest = "\
+[dependencies]
+serde = \"1\"
+grader-utils = \"1\" # compose::private(no_hint)
+";
+        assert_eq!(
+            compose_file("Cargo.toml", manifest, &[]).unwrap(),
+            "[dependencies]\nserde = \"1\"\n"
+        );
+
+        let readme = "\
+# Problem
+<!-- compose::begin_private(hint(\"Grading is described in the course rules.\")) -->
+Hidden tests are worth 50%.
+<!-- compose::end_private -->
+<!-- compose::begin_public -->
+<!-- Good luck! -->
+<!-- compose::end_public -->
+";
+        assert_eq!(
+            compose_file("README.md", readme, &[]).unwrap(),
+            "# Problem\n<!-- Grading is described in the course rules. -->\nGood luck!\n"
+        );
+
+        let test = "def test():\n    # compose::begin_private(unimplemented)\n    pass\n    # compose::end_private\n";
+        assert_eq!(
+            compose_file("test.py", test, &[]).unwrap(),
+            "def test():\n    # TODO: your code goes here.\n    raise NotImplementedError()\n"
+        );
+        assert!(compose_file("Cargo.toml", "# compose::private(unimplemented)\n", &[]).is_err());
+    }
+
+    #[test]
+    fn markers_in_literals_are_text() {
+        let src = "\
+//! Mark the solution with `// compose::private`.
+
+const TEMPLATE: &str = \"
+    // compose::begin_private
+\";
+";
+        assert_eq!(compose(src, &[]).unwrap(), src);
+    }
+
+    #[test]
+    fn invalid_markers() {
+        for src in [
+            "// compose::end_private\n",
+            "// compose::begin_private\n",
+            "// compose::begin_private\n// compose::begin_public\n",
+            "// compose::begin_private\n// compose::end_public\n",
+            "// compose::private(hint)\n",
+            "// compose::private(replace_with(\"x)\n",
+            "// compose::private(no_hint, hint(\"x\"))\n",
+            "// compose::end_private(no_hint)\n",
+            "// compose::begin_public(unimplemented)\n",
+            "// compose::secret\n",
+        ] {
+            assert!(compose(src, &[]).is_err(), "{src:?} is accepted");
+        }
     }
 }
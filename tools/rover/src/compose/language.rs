@@ -0,0 +1,165 @@
+use proc_macro2::{LineColumn, TokenStream, TokenTree};
+use std::path::Path;
+
+/// How a comment is written in a language, `end` is empty for line comments.
+pub struct Comment {
+    pub start: &'static str,
+    pub end: &'static str,
+}
+
+impl Comment {
+    pub fn wrap(&self, text: &str) -> String {
+        if self.end.is_empty() {
+            format!("{} {text}", self.start)
+        } else {
+            format!("{} {text} {}", self.start, self.end)
+        }
+    }
+
+    /// Returns the text of the comment if the whole line is one.
+    pub fn unwrap<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let text = line.trim().strip_prefix(self.start)?;
+        let text = text.strip_suffix(self.end)?;
+        Some(text.strip_prefix(' ').unwrap_or(text).trim_end())
+    }
+}
+
+/// A syntax error in a composed file and its 1-based line.
+pub struct SyntaxError {
+    pub message: String,
+    pub line: usize,
+}
+
+/// Checks that a composed file is still valid.
+type Verify = fn(&str) -> Result<(), SyntaxError>;
+
+/// Finds the regions of a file which may look like markers, but are not, e.g. string literals.
+type Opaque = fn(&str) -> Vec<(LineColumn, LineColumn)>;
+
+/// The format of the files compose puts markers in.
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    pub comment: Comment,
+    /// The code put by the `unimplemented` property.
+    pub unimplemented: Option<&'static str>,
+    pub verify: Option<Verify>,
+    pub opaque: Option<Opaque>,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        comment: Comment {
+            start: "//",
+            end: "",
+        },
+        unimplemented: Some("unimplemented!()"),
+        verify: Some(verify_rust),
+        opaque: Some(rust_literals),
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        comment: Comment {
+            start: "#",
+            end: "",
+        },
+        unimplemented: None,
+        verify: Some(verify_toml),
+        opaque: None,
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        comment: Comment {
+            start: "#",
+            end: "",
+        },
+        unimplemented: None,
+        verify: Some(verify_yaml),
+        opaque: None,
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md"],
+        comment: Comment {
+            start: "<!--",
+            end: "-->",
+        },
+        unimplemented: None,
+        verify: None,
+        opaque: None,
+    },
+    Language {
+        name: "Python",
+        extensions: &["py"],
+        comment: Comment {
+            start: "#",
+            end: "",
+        },
+        unimplemented: Some("raise NotImplementedError()"),
+        verify: None,
+        opaque: None,
+    },
+];
+
+/// The language of the file by its extension, files of other languages are copied as-is.
+pub fn language_of(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?;
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension))
+}
+
+fn verify_rust(content: &str) -> Result<(), SyntaxError> {
+    syn::parse_file(content)
+        .map(|_| ())
+        .map_err(|err| SyntaxError {
+            message: err.to_string(),
+            line: err.span().start().line,
+        })
+}
+
+fn verify_toml(content: &str) -> Result<(), SyntaxError> {
+    toml::from_str::<toml::Value>(content)
+        .map(|_| ())
+        .map_err(|err| SyntaxError {
+            message: err.message().to_string(),
+            line: err
+                .span()
+                .map_or(0, |span| content[..span.start].matches('\n').count() + 1),
+        })
+}
+
+fn verify_yaml(content: &str) -> Result<(), SyntaxError> {
+    serde_yaml::from_str::<serde_yaml::Value>(content)
+        .map(|_| ())
+        .map_err(|err| SyntaxError {
+            message: err.to_string(),
+            line: err.location().map_or(0, |location| location.line()),
+        })
+}
+
+/// String literals and doc comments, which may contain markers as text, e.g. in templates.
+fn rust_literals(content: &str) -> Vec<(LineColumn, LineColumn)> {
+    fn collect(stream: TokenStream, literals: &mut Vec<(LineColumn, LineColumn)>) {
+        for tree in stream {
+            match tree {
+                TokenTree::Group(group) => collect(group.stream(), literals),
+                TokenTree::Literal(literal) => {
+                    let span = literal.span();
+                    literals.push((span.start(), span.end()));
+                }
+                TokenTree::Ident(_) | TokenTree::Punct(_) => {}
+            }
+        }
+    }
+    let mut literals = Vec::new();
+    // Markers in the files which don't lex are still processed, verification will complain
+    if let Ok(stream) = content.parse() {
+        collect(stream, &mut literals);
+    }
+    literals
+}
@@ -1,8 +1,11 @@
 mod cargo_root;
+mod check;
 pub mod config;
 mod dir;
 mod file;
+mod language;
 mod process;
-mod prune;
+mod protect;
 pub mod run_compose;
 mod skip;
+mod sync;
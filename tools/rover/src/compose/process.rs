@@ -1,14 +1,20 @@
-use super::{config::Config, dir::process_dir, file::process_file};
+use super::{config::Config, dir::process_dir, file::FileProcessor};
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 
-pub fn process(input: &Path, output: &Path, config: &Config) -> Result<()> {
+/// Composes the entries of the config, the output paths are relative to the output repository.
+pub fn process(
+    input: &Path,
+    problems_folder: &Path,
+    config: &Config,
+    processor: &mut FileProcessor,
+) -> Result<()> {
     let to_process = std::iter::empty()
         .chain(
             config
                 .get_problems()
                 .iter()
-                .map(|path| PathBuf::from("problems").join(path)),
+                .map(|path| problems_folder.join(path)),
         )
         .chain(
             config
@@ -19,11 +25,10 @@ pub fn process(input: &Path, output: &Path, config: &Config) -> Result<()> {
         .chain(config.get_copy().iter().cloned());
     for entry in to_process {
         let input = input.join(&entry);
-        let output = output.join(&entry);
         if input.is_dir() {
-            process_dir(&input, &output)?;
+            process_dir(&input, &entry, processor)?;
         } else {
-            process_file(&input, &output)?;
+            processor.process_file(&input, &entry)?;
         }
     }
     Ok(())
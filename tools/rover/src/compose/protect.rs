@@ -0,0 +1,136 @@
+use super::{config::Config, file::Content};
+use crate::repository::{
+    protected::{Manifest, PROTECTED_FILE},
+    repo::{Repository, SETTINGS_FILE},
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use toml_edit::{value, Document};
+
+/// Sets `composed` in the settings of the public repository, so that `rover test` there
+/// fails on the problems without the manifest. The settings are made anew if not copied.
+fn mark_composed(repository: &Repository, files: &mut BTreeMap<PathBuf, Content>) -> Result<()> {
+    let path = PathBuf::from(SETTINGS_FILE);
+    let mut settings = match files.get(&path) {
+        Some(content) => String::from_utf8(content.read()?.into_owned())
+            .context("composed settings are not UTF-8")?
+            .parse::<Document>()
+            .context("composed settings are not valid TOML")?,
+        None => {
+            let mut settings = Document::new();
+            if repository.problems_folder() != Path::new("problems") {
+                let folder = repository.problems_folder().to_str().unwrap();
+                settings["problems-folder"] = value(folder);
+            }
+            settings
+        }
+    };
+    settings["composed"] = value(true);
+    files.insert(path, Content::Text(settings.to_string()));
+    Ok(())
+}
+
+/// Adds the manifest of the files outside of `allowed-patterns` to every composed problem.
+pub fn protect(
+    repository: &Repository,
+    config: &Config,
+    files: &mut BTreeMap<PathBuf, Content>,
+) -> Result<()> {
+    for entry in config.get_problems() {
+        let problem = repository.problem_from_path(
+            &repository
+                .get_path()
+                .join(repository.problems_folder())
+                .join(entry),
+        )?;
+        let relative_path = problem.relative_path();
+        let allowed = problem
+            .solution_files()
+            .with_context(|| format!("failed to protect problem {}", problem.branch_name()))?;
+        let mut manifest = Manifest::default();
+        for (path, content) in files.range(relative_path.clone()..) {
+            let path_in_problem = match path.strip_prefix(&relative_path) {
+                Ok(path_in_problem) => path_in_problem,
+                Err(_) => break,
+            };
+            if !allowed.contains(path) {
+                manifest.insert(path_in_problem.to_path_buf(), &content.read()?);
+            }
+        }
+        files.insert(
+            relative_path.join(PROTECTED_FILE),
+            Content::Text(manifest.to_yml()?),
+        );
+    }
+    mark_composed(repository, files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn text(files: &BTreeMap<PathBuf, Content>, path: &str) -> String {
+        String::from_utf8(files[Path::new(path)].read().unwrap().into_owned()).unwrap()
+    }
+
+    #[test]
+    fn manifest_lists_non_solution_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let problem = root.join("problems/tutorial/add");
+        fs::create_dir_all(problem.join("src")).unwrap();
+        fs::write(root.join(".rover.toml"), "").unwrap();
+        fs::write(
+            problem.join(".config.yml"),
+            "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
+        )
+        .unwrap();
+        fs::write(problem.join("src/lib.rs"), "").unwrap();
+        let repository = Repository::from_path(root).unwrap();
+        let config_path = root.join(".compose.yml");
+        fs::write(
+            &config_path,
+            "problems: [tutorial/add]\ntools: []\ncopy: []\nskip-entries: []\n\
+             add-to-toml: []\ndo-not-delete: []\n",
+        )
+        .unwrap();
+        let config = Config::from_yml(&config_path).unwrap();
+
+        let prefix = Path::new("problems/tutorial/add");
+        let mut files = BTreeMap::new();
+        for (path, content) in [
+            ("src/lib.rs", "solution"),
+            ("Cargo.toml", "[package]"),
+            ("build.rs", "fn main() {}"),
+            (".config.yml", "steps: {}"),
+            ("tests/tests.rs", "#[test]"),
+        ] {
+            files.insert(prefix.join(path), Content::Text(content.to_string()));
+        }
+        files.insert(PathBuf::from("README.md"), Content::Text(String::new()));
+        protect(&repository, &config, &mut files).unwrap();
+
+        let manifest_path = dir.path().join("manifest.yml");
+        fs::write(
+            &manifest_path,
+            text(&files, "problems/tutorial/add/.protected.yml"),
+        )
+        .unwrap();
+        let manifest = Manifest::from_yml(&manifest_path).unwrap();
+        // Nothing is written yet, so every listed file is reported as missing
+        assert_eq!(
+            manifest.modified_files(&dir.path().join("empty")),
+            [
+                Path::new(".config.yml"),
+                Path::new("Cargo.toml"),
+                Path::new("build.rs"),
+                Path::new("tests/tests.rs"),
+            ]
+        );
+        assert_eq!(text(&files, ".rover.toml"), "composed = true\n");
+    }
+}
@@ -1,9 +1,23 @@
-use super::{cargo_root::cargo_root, process::process, prune::prune, skip::skip};
+use super::{
+    cargo_root::cargo_root, check::check_workspace, file::FileProcessor, process::process,
+    protect::protect, skip::skip, sync::Plan,
+};
 use crate::repository::repo::Repository;
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::{collections::BTreeSet, path::Path};
 
-pub fn run_compose(input: &Path, output: &Path) -> Result<()> {
+pub struct ComposeOptions {
+    /// Enabled in addition to the features of the config.
+    pub features: Vec<String>,
+    /// Type-check the composed workspace with cargo.
+    pub check: bool,
+    /// Only print the changes to the output repository.
+    pub plan: bool,
+    /// Leave the unchanged files of the output repository untouched.
+    pub incremental: bool,
+}
+
+pub fn run_compose(input: &Path, output: &Path, options: &ComposeOptions) -> Result<()> {
     let repository = Repository::from_path(input)?;
     let config = repository.compose_config()?;
     let input = repository.get_path().to_path_buf();
@@ -17,8 +31,32 @@ pub fn run_compose(input: &Path, output: &Path) -> Result<()> {
                 .file_name()
                 .context("output path has no file name to canonicalize")?,
         );
-    prune(&output, &config)?;
-    process(&input, &output, &config)?;
-    cargo_root(&output, &config)?;
-    skip(&output, &config)
+    let features: BTreeSet<_> = config
+        .get_features()
+        .iter()
+        .chain(&options.features)
+        .cloned()
+        .collect();
+    let mut processor = FileProcessor::new(features);
+    let problems_folder = repository.problems_folder();
+    process(&input, problems_folder, &config, &mut processor)?;
+    cargo_root(&input, problems_folder, &mut processor, &config)?;
+    skip(processor.files(), &config)?;
+    protect(&repository, &config, processor.files())?;
+    let plan = Plan::new(
+        &output,
+        processor.files(),
+        &config.do_not_delete_patterns()?,
+    )?;
+    if options.plan {
+        plan.print();
+        return Ok(());
+    }
+    plan.apply(&output, processor.files(), options.incremental)?;
+    plan.print_summary();
+    if options.check {
+        let target_dir = repository.target_dir().join("compose");
+        check_workspace(&output, &target_dir, processor.source_maps())?;
+    }
+    Ok(())
 }
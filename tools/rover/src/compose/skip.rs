@@ -1,16 +1,14 @@
-use super::config::Config;
-use anyhow::{Context, Result};
-use std::{fs, path::Path};
+use super::{config::Config, file::Content};
+use anyhow::{bail, Result};
+use std::{collections::BTreeMap, path::PathBuf};
 
-pub fn skip(output: &Path, config: &Config) -> Result<()> {
+pub fn skip(files: &mut BTreeMap<PathBuf, Content>, config: &Config) -> Result<()> {
     for entry in config.get_skipped() {
-        let path = output.join(entry);
-        if path.is_dir() {
-            fs::remove_dir_all(&path)
-        } else {
-            fs::remove_file(&path)
+        let count = files.len();
+        files.retain(|path, _| !path.starts_with(entry));
+        if files.len() == count {
+            bail!("skipped entry {entry:?} is not composed")
         }
-        .with_context(|| format!("failed to remove {path:?}"))?;
     }
     Ok(())
 }
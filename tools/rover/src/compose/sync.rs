@@ -0,0 +1,236 @@
+use super::file::Content;
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The changes bringing the output repository in sync with the composed files.
+#[derive(Default)]
+pub struct Plan {
+    created: Vec<PathBuf>,
+    modified: Vec<PathBuf>,
+    unchanged: Vec<PathBuf>,
+    deleted: Vec<PathBuf>,
+}
+
+fn is_protected(path: &Path, protected: &[Pattern]) -> bool {
+    protected
+        .iter()
+        .any(|pattern| pattern.matches_path_with(path, MATCH_OPTIONS))
+}
+
+/// Lists the files of the output repository which are not protected by do-not-delete.
+/// Symlinks are listed as files and never followed, so that pruning stays inside `output`.
+fn existing_files(
+    output: &Path,
+    dir: &Path,
+    protected: &[Pattern],
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let path = output.join(dir);
+    for entry in
+        fs::read_dir(&path).with_context(|| format!("failed to read directory {path:?}"))?
+    {
+        let entry = entry.with_context(|| format!("failed to read entry in directory {path:?}"))?;
+        let relative = dir.join(entry.file_name());
+        if is_protected(&relative, protected) {
+            continue;
+        }
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("failed to get file type of {:?}", entry.path()))?;
+        if file_type.is_dir() {
+            existing_files(output, &relative, protected, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the directories left empty after removing the file, up to the output repository.
+fn remove_empty_parents(output: &Path, file: &Path) -> Result<()> {
+    let mut dir = file.parent();
+    while let Some(path) = dir {
+        let is_empty = fs::read_dir(path)
+            .with_context(|| format!("failed to read directory {path:?}"))?
+            .next()
+            .is_none();
+        if path == output || !is_empty {
+            break;
+        }
+        fs::remove_dir(path).with_context(|| format!("failed to remove {path:?}"))?;
+        dir = path.parent();
+    }
+    Ok(())
+}
+
+impl Plan {
+    pub fn new(
+        output: &Path,
+        files: &BTreeMap<PathBuf, Content>,
+        protected: &[Pattern],
+    ) -> Result<Self> {
+        let mut plan = Self::default();
+        let mut existing = Vec::new();
+        if output.is_dir() {
+            existing_files(output, Path::new(""), protected, &mut existing)?;
+        }
+        existing.sort();
+        plan.deleted = existing
+            .into_iter()
+            .filter(|path| !files.contains_key(path))
+            .collect();
+        for (path, content) in files {
+            match fs::read(output.join(path)) {
+                Ok(old) if old == *content.read()? => plan.unchanged.push(path.clone()),
+                Ok(_) => plan.modified.push(path.clone()),
+                Err(_) => plan.created.push(path.clone()),
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn print(&self) {
+        for path in &self.created {
+            println!("create {}", path.display());
+        }
+        for path in &self.modified {
+            println!("modify {}", path.display());
+        }
+        for path in &self.deleted {
+            println!("delete {}", path.display());
+        }
+        if !self.created.is_empty() || !self.modified.is_empty() || !self.deleted.is_empty() {
+            println!();
+        }
+        self.print_summary();
+    }
+
+    pub fn print_summary(&self) {
+        println!(
+            "{} created, {} modified, {} deleted, {} unchanged",
+            self.created.len(),
+            self.modified.len(),
+            self.deleted.len(),
+            self.unchanged.len()
+        );
+    }
+
+    /// Applies the plan, rewriting the unchanged files as well unless `incremental`.
+    pub fn apply(
+        &self,
+        output: &Path,
+        files: &BTreeMap<PathBuf, Content>,
+        incremental: bool,
+    ) -> Result<()> {
+        for path in &self.deleted {
+            let path = output.join(path);
+            fs::remove_file(&path).with_context(|| format!("failed to remove {path:?}"))?;
+            remove_empty_parents(output, &path)?;
+        }
+        let unchanged = if incremental {
+            &[]
+        } else {
+            self.unchanged.as_slice()
+        };
+        for path in self.created.iter().chain(&self.modified).chain(unchanged) {
+            files[path].write(&output.join(path))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn plan_and_apply() {
+        let output = tempfile::tempdir().unwrap();
+        let output = output.path();
+        for path in [
+            "same.rs",
+            "changed.rs",
+            "stale/nested/old.rs",
+            ".git/HEAD",
+            "docs/img/logo.png",
+            "docs/notes.md",
+        ] {
+            write(output, path, "old");
+        }
+        let files = BTreeMap::from([
+            (PathBuf::from("same.rs"), Content::Text("old".to_string())),
+            (
+                PathBuf::from("changed.rs"),
+                Content::Text("new".to_string()),
+            ),
+            (
+                PathBuf::from("src/new.rs"),
+                Content::Text("new".to_string()),
+            ),
+        ]);
+        let protected = [
+            Pattern::new(".git").unwrap(),
+            Pattern::new("docs/**/*.png").unwrap(),
+        ];
+
+        let plan = Plan::new(output, &files, &protected).unwrap();
+        assert_eq!(plan.created, [PathBuf::from("src/new.rs")]);
+        assert_eq!(plan.modified, [PathBuf::from("changed.rs")]);
+        assert_eq!(plan.unchanged, [PathBuf::from("same.rs")]);
+        assert_eq!(
+            plan.deleted,
+            [
+                PathBuf::from("docs/notes.md"),
+                PathBuf::from("stale/nested/old.rs")
+            ]
+        );
+
+        plan.apply(output, &files, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(output.join("changed.rs")).unwrap(),
+            "new"
+        );
+        assert!(output.join("src/new.rs").is_file());
+        assert!(output.join(".git/HEAD").is_file());
+        assert!(output.join("docs/img/logo.png").is_file());
+        assert!(!output.join("docs/notes.md").exists());
+        assert!(!output.join("stale").exists());
+
+        let plan = Plan::new(output, &files, &protected).unwrap();
+        assert!(plan.created.is_empty() && plan.modified.is_empty() && plan.deleted.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let root = tempfile::tempdir().unwrap();
+        let output = root.path().join("output");
+        write(root.path(), "outside/keep.rs", "keep");
+        write(&output, "same.rs", "old");
+        std::os::unix::fs::symlink(root.path().join("outside"), output.join("link")).unwrap();
+        let files = BTreeMap::from([(PathBuf::from("same.rs"), Content::Text("old".to_string()))]);
+
+        let plan = Plan::new(&output, &files, &[]).unwrap();
+        assert_eq!(plan.deleted, [PathBuf::from("link")]);
+        plan.apply(&output, &files, true).unwrap();
+        assert!(!output.join("link").exists());
+        assert!(root.path().join("outside/keep.rs").is_file());
+    }
+}
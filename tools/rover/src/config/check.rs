@@ -0,0 +1,72 @@
+use crate::repository::repo::Repository;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Validates `.config.yml` of every problem in the course repository.
+pub fn check_configs(path: &Path) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let problems = repository.problems()?;
+    let mut failed = 0;
+    for problem in &problems {
+        match problem.config() {
+            Ok(config) => {
+                if config.get_relative_user_files().is_empty() {
+                    println!(
+                        "[warn] {}: allowed-patterns match no files",
+                        problem.branch_name()
+                    );
+                } else {
+                    println!("[ok]   {}", problem.branch_name());
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                println!("[FAIL] {}: {err:#}", problem.branch_name());
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} configs are invalid", problems.len())
+    }
+    println!("\nAll {} configs are valid", problems.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn counts_invalid_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".rover.toml"), "").unwrap();
+        for (problem, config) in [
+            (
+                "add",
+                "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
+            ),
+            (
+                "sub",
+                "steps: {tests: [cargo-test]}\nlimits: {memory: lots}\n",
+            ),
+            ("mul", "steps: {tests: [{program: x, cwd: ../..}]}\n"),
+        ] {
+            let path = dir.path().join("problems/intro").join(problem);
+            fs::create_dir_all(path.join("src")).unwrap();
+            fs::write(path.join("src/lib.rs"), "").unwrap();
+            fs::write(path.join(".config.yml"), config).unwrap();
+        }
+        let err = check_configs(dir.path()).unwrap_err();
+        assert_eq!(err.to_string(), "2 of 3 configs are invalid");
+
+        let path = dir.path().join("problems/intro");
+        fs::write(
+            path.join("sub/.config.yml"),
+            "steps: {tests: [cargo-test]}\n",
+        )
+        .unwrap();
+        fs::remove_dir_all(path.join("mul")).unwrap();
+        check_configs(dir.path()).unwrap();
+    }
+}
@@ -0,0 +1 @@
+pub mod run_doctor;
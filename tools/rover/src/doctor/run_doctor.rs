@@ -0,0 +1,421 @@
+use crate::{
+    git::git_repo::{GitRepo, REMOTE},
+    repository::{problem::Problem, repo::Repository, settings::Settings},
+};
+use anyhow::{bail, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    path::{Path, PathBuf},
+    process,
+};
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Requirement {
+    Rustup,
+    Toolchain(String),
+    Component(String, String),
+    Program(Option<String>, String),
+}
+
+struct Check {
+    description: String,
+    problem: Option<String>,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(description: String) -> Self {
+        Self {
+            description,
+            problem: None,
+            fix: None,
+        }
+    }
+
+    fn failed(description: String, problem: String, fix: Option<String>) -> Self {
+        Self {
+            description,
+            problem: Some(problem),
+            fix,
+        }
+    }
+
+    fn print(&self) {
+        match &self.problem {
+            None => println!("[ok]   {}", self.description),
+            Some(problem) => {
+                println!("[FAIL] {}: {problem}", self.description);
+                if let Some(fix) = &self.fix {
+                    println!("       fix: {fix}");
+                }
+            }
+        }
+    }
+}
+
+/// The tools installed on the machine, replaced in the tests.
+trait Environment {
+    /// Runs the program and returns its output if it succeeds.
+    fn output(&self, program: &str, args: &[&str]) -> Option<String>;
+    fn find_in_path(&self, program: &str) -> Option<PathBuf>;
+}
+
+struct System;
+
+impl Environment for System {
+    fn output(&self, program: &str, args: &[&str]) -> Option<String> {
+        let output = process::Command::new(program).args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn find_in_path(&self, program: &str) -> Option<PathBuf> {
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    }
+}
+
+fn collect_requirements(
+    problems: &[Problem],
+    checks: &mut Vec<Check>,
+) -> BTreeMap<Requirement, BTreeSet<String>> {
+    let mut requirements: BTreeMap<Requirement, BTreeSet<String>> = BTreeMap::new();
+    for problem in problems {
+        let name = problem.branch_name();
+        let config = match problem.config() {
+            Ok(config) => config,
+            Err(err) => {
+                checks.push(Check::failed(
+                    format!("config of {name} is valid"),
+                    format!("{err:#}"),
+                    Some(format!("fix {:?}", problem.config_path())),
+                ));
+                continue;
+            }
+        };
+        let toolchain = config.get_toolchain().rustup_name().map(String::from);
+        let mut require = |requirement: Requirement| {
+            requirements
+                .entry(requirement)
+                .or_default()
+                .insert(name.clone());
+        };
+        if let Some(toolchain) = &toolchain {
+            require(Requirement::Rustup);
+            require(Requirement::Toolchain(toolchain.clone()));
+        }
+        for entry in config.get_steps().iter().flat_map(|step| step.commands()) {
+            let command = &entry.command;
+            for component in command.required_components() {
+                let toolchain = toolchain.clone().unwrap_or_else(|| "default".to_string());
+                require(Requirement::Component(toolchain, component.to_string()));
+            }
+            if let Some(program) = command.required_program() {
+                // Programs given by path are part of the problem itself
+                if !program.contains('/') {
+                    require(Requirement::Program(toolchain.clone(), program.to_string()));
+                }
+            }
+        }
+    }
+    requirements
+}
+
+fn check_requirement(
+    environment: &impl Environment,
+    requirement: &Requirement,
+    required_by: &BTreeSet<String>,
+) -> Check {
+    let users = required_by.iter().cloned().collect::<Vec<_>>().join(", ");
+    match requirement {
+        Requirement::Rustup => match environment.output("rustup", &["--version"]) {
+            Some(_) => Check::ok("rustup is installed".to_string()),
+            None => Check::failed(
+                "rustup is installed".to_string(),
+                format!("rustup is not found, but it's required by {users}"),
+                Some("install rustup from https://rustup.rs".to_string()),
+            ),
+        },
+        Requirement::Toolchain(toolchain) => {
+            let description = format!("toolchain {toolchain} is installed");
+            let installed = environment
+                .output("rustup", &["toolchain", "list"])
+                .map(|list| {
+                    list.lines()
+                        .any(|line| line.starts_with(&format!("{toolchain}-")))
+                })
+                .unwrap_or(false);
+            if installed {
+                Check::ok(description)
+            } else {
+                Check::failed(
+                    description,
+                    format!("required by {users}"),
+                    Some(format!("rustup toolchain install {toolchain}")),
+                )
+            }
+        }
+        Requirement::Component(toolchain, component) => {
+            let description = format!("component {component} is installed for {toolchain}");
+            let mut args = vec!["component", "list", "--installed"];
+            if toolchain != "default" {
+                args.extend(["--toolchain", toolchain]);
+            }
+            let installed = environment
+                .output("rustup", &args)
+                .map(|list| {
+                    list.lines()
+                        .any(|line| line.starts_with(&format!("{component}-")))
+                })
+                .unwrap_or(false);
+            if installed {
+                Check::ok(description)
+            } else {
+                let fix = if toolchain == "default" {
+                    format!("rustup component add {component}")
+                } else {
+                    format!("rustup component add --toolchain {toolchain} {component}")
+                };
+                Check::failed(description, format!("required by {users}"), Some(fix))
+            }
+        }
+        Requirement::Program(toolchain, program) => {
+            let description = match toolchain {
+                Some(toolchain) => format!("program {program} is available with {toolchain}"),
+                None => format!("program {program} is available"),
+            };
+            let in_toolchain = toolchain.as_ref().is_some_and(|toolchain| {
+                environment
+                    .output("rustup", &["which", "--toolchain", toolchain, program])
+                    .is_some()
+            });
+            if in_toolchain || environment.find_in_path(program).is_some() {
+                Check::ok(description)
+            } else {
+                Check::failed(
+                    description,
+                    format!("not found in PATH, but it's required by {users}"),
+                    Some(format!("install {program} and make sure it is in PATH")),
+                )
+            }
+        }
+    }
+}
+
+fn check_solutions_repo(
+    settings: &Settings,
+    solutions_repo: &Path,
+    problems: &[Problem],
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let description = format!("{solutions_repo:?} is a git repository");
+    let git = match GitRepo::open(solutions_repo) {
+        Ok(git) => git,
+        Err(err) => {
+            checks.push(Check::failed(
+                description,
+                format!("{err:#}"),
+                Some(format!(
+                    "git clone YOUR_SOLUTIONS_REPOSITORY {solutions_repo:?}"
+                )),
+            ));
+            return checks;
+        }
+    };
+    checks.push(Check::ok(description));
+    match git.remote_url() {
+        Ok(url) => checks.push(Check::ok(format!("remote {REMOTE} is set to {url}"))),
+        Err(err) => {
+            checks.push(Check::failed(
+                format!("remote {REMOTE} is set"),
+                format!("{err:#}"),
+                Some(format!("git remote add {REMOTE} YOUR_SOLUTIONS_REPOSITORY")),
+            ));
+            return checks;
+        }
+    }
+    match git.default_branch() {
+        Ok(branch) => checks.push(Check::ok(format!("default branch is {branch}"))),
+        Err(err) => checks.push(Check::failed(
+            "default branch exists".to_string(),
+            format!("{err:#}"),
+            Some(format!(
+                "git fetch {REMOTE} && git remote set-head {REMOTE} --auto"
+            )),
+        )),
+    }
+    let description = "problem branches match the course problems".to_string();
+    let known: BTreeSet<_> = problems.iter().map(Problem::branch_name).collect();
+    match git.local_branches() {
+        Ok(branches) => {
+            let unknown: Vec<_> = branches
+                .into_iter()
+                .filter(|branch| settings.is_problem_branch(branch) && !known.contains(branch))
+                .collect();
+            if unknown.is_empty() {
+                checks.push(Check::ok(description));
+            } else {
+                checks.push(Check::failed(
+                    description,
+                    format!("unknown branches {}", unknown.join(", ")),
+                    Some("rename or delete the branches with git branch -m/-d".to_string()),
+                ));
+            }
+        }
+        Err(err) => checks.push(Check::failed(description, format!("{err:#}"), None)),
+    }
+    checks
+}
+
+pub fn run_doctor(path: &Path, solutions_repo: Option<PathBuf>) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let problems = repository.problems()?;
+    let mut checks = Vec::new();
+    let requirements = collect_requirements(&problems, &mut checks);
+    for (requirement, required_by) in &requirements {
+        checks.push(check_requirement(&System, requirement, required_by));
+    }
+    let solutions_repo = match solutions_repo {
+        Some(path) => Ok(path),
+        None => repository.solutions_repo(),
+    };
+    match solutions_repo {
+        Ok(solutions_repo) => checks.extend(check_solutions_repo(repository.settings(), &solutions_repo, &problems)),
+        Err(err) => checks.push(Check::failed(
+            "solutions repository exists".to_string(),
+            format!("{err:#}"),
+            Some("clone your solutions repository there, set solutions-repo in .rover.toml, or pass --solutions-repo".to_string()),
+        )),
+    }
+    for check in &checks {
+        check.print();
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.problem.is_some())
+        .count();
+    if failed > 0 {
+        bail!("{failed} of {} checks failed", checks.len())
+    }
+    println!("\nEverything is fine!");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Answers the commands by their full lines, the rest fail.
+    #[derive(Default)]
+    struct Fake {
+        outputs: HashMap<String, String>,
+        path: Vec<String>,
+    }
+
+    impl Environment for Fake {
+        fn output(&self, program: &str, args: &[&str]) -> Option<String> {
+            let line = std::iter::once(program)
+                .chain(args.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.outputs.get(&line).cloned()
+        }
+
+        fn find_in_path(&self, program: &str) -> Option<PathBuf> {
+            self.path
+                .iter()
+                .any(|name| name == program)
+                .then(|| Path::new("/usr/bin").join(program))
+        }
+    }
+
+    fn check(environment: &Fake, requirement: Requirement) -> Check {
+        let required_by = BTreeSet::from(["intro/add".to_string()]);
+        check_requirement(environment, &requirement, &required_by)
+    }
+
+    #[test]
+    fn toolchain_checks() {
+        let mut environment = Fake::default();
+        let rustup = check(&environment, Requirement::Rustup);
+        assert_eq!(
+            rustup.problem.as_deref(),
+            Some("rustup is not found, but it's required by intro/add")
+        );
+        environment.outputs.extend([
+            ("rustup --version".to_string(), "rustup 1.27.1".to_string()),
+            (
+                "rustup toolchain list".to_string(),
+                "stable-x86_64-unknown-linux-gnu (default)".to_string(),
+            ),
+            (
+                "rustup component list --installed --toolchain nightly".to_string(),
+                "miri-x86_64-unknown-linux-gnu".to_string(),
+            ),
+        ]);
+        assert!(check(&environment, Requirement::Rustup).problem.is_none());
+        let stable = Requirement::Toolchain("stable".to_string());
+        assert!(check(&environment, stable).problem.is_none());
+        let nightly = check(&environment, Requirement::Toolchain("nightly".to_string()));
+        assert_eq!(nightly.problem.as_deref(), Some("required by intro/add"));
+        assert_eq!(
+            nightly.fix.as_deref(),
+            Some("rustup toolchain install nightly")
+        );
+
+        let component =
+            |name: &str| Requirement::Component("nightly".to_string(), name.to_string());
+        assert!(check(&environment, component("miri")).problem.is_none());
+        assert_eq!(
+            check(&environment, component("clippy")).fix.as_deref(),
+            Some("rustup component add --toolchain nightly clippy")
+        );
+    }
+
+    #[test]
+    fn program_checks() {
+        let mut environment = Fake::default();
+        let python = Requirement::Program(None, "python3".to_string());
+        let missing = check(&environment, python.clone());
+        assert_eq!(
+            missing.problem.as_deref(),
+            Some("not found in PATH, but it's required by intro/add")
+        );
+        environment.path.push("python3".to_string());
+        assert!(check(&environment, python).problem.is_none());
+
+        let nextest =
+            Requirement::Program(Some("nightly".to_string()), "cargo-nextest".to_string());
+        assert!(check(&environment, nextest.clone()).problem.is_some());
+        environment.outputs.insert(
+            "rustup which --toolchain nightly cargo-nextest".to_string(),
+            "/root/.cargo/bin/cargo-nextest".to_string(),
+        );
+        assert!(check(&environment, nextest).problem.is_none());
+    }
+
+    #[test]
+    fn solutions_repo_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::default();
+        let missing = dir.path().join("solutions");
+        let checks = check_solutions_repo(&settings, &missing, &[]);
+        assert_eq!(checks.len(), 1);
+        assert!(checks[0].problem.is_some());
+        assert!(checks[0].fix.as_deref().unwrap().starts_with("git clone"));
+
+        git2::Repository::init(&missing).unwrap();
+        let checks = check_solutions_repo(&settings, &missing, &[]);
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].problem.is_none());
+        assert_eq!(
+            checks[1].fix.as_deref(),
+            Some("git remote add origin YOUR_SOLUTIONS_REPOSITORY")
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use git2::{Config, Cred, CredentialType, Error};
+use std::{env, path::PathBuf};
+
+const SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Tries the ways of authentication the git command line would try, each one once,
+/// since libgit2 keeps asking for credentials until the callback fails.
+#[derive(Default)]
+pub struct Credentials {
+    tried_agent: bool,
+    tried_keys: usize,
+    tried_helper: bool,
+    tried_default: bool,
+}
+
+impl Credentials {
+    pub fn get(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, Error> {
+        let username = username_from_url.unwrap_or("git");
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !self.tried_agent {
+                self.tried_agent = true;
+                return Cred::ssh_key_from_agent(username);
+            }
+            if let Some(home) = env::var_os("HOME") {
+                while self.tried_keys < SSH_KEYS.len() {
+                    let key = PathBuf::from(&home)
+                        .join(".ssh")
+                        .join(SSH_KEYS[self.tried_keys]);
+                    self.tried_keys += 1;
+                    if key.is_file() {
+                        return Cred::ssh_key(username, None, &key, None);
+                    }
+                }
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !self.tried_helper {
+            self.tried_helper = true;
+            let config = Config::open_default()?;
+            return Cred::credential_helper(&config, url, username_from_url);
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !self.tried_default {
+            self.tried_default = true;
+            return Cred::default();
+        }
+        Err(Error::from_str(&format!(
+            "no credentials for {url} were accepted, check \"git push\" works from the command line"
+        )))
+    }
+}
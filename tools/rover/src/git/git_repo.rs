@@ -0,0 +1,653 @@
+use super::credentials::Credentials;
+use anyhow::{bail, Context, Result};
+use git2::{
+    build::CheckoutBuilder, BranchType, Direction, FetchOptions, Oid, Patch, PushOptions,
+    RemoteCallbacks, Repository, Status, StatusOptions,
+};
+use std::{
+    cell::RefCell,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+pub const REMOTE: &str = "origin";
+const FALLBACK_DEFAULT_BRANCHES: [&str; 2] = ["master", "main"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Untracked,
+    New,
+    Modified,
+    Deleted,
+    Renamed,
+    Conflicted,
+}
+
+/// A file which differs from the last commit, either in the index or in the working tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Untracked => "untracked",
+            ChangeKind::New => "new",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Renamed => "renamed",
+            ChangeKind::Conflicted => "conflicted",
+        };
+        write!(f, "{} ({kind})", self.path.display())
+    }
+}
+
+pub fn describe_changes(changes: &[Change]) -> String {
+    changes
+        .iter()
+        .map(Change::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Unified diff of a file together with the numbers of added and deleted lines.
+pub struct FileDiff {
+    pub text: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+pub fn diff_buffers(path: &Path, old: &[u8], new: &[u8]) -> Result<FileDiff> {
+    let mut patch = Patch::from_buffers(old, Some(path), new, Some(path), None)
+        .with_context(|| format!("failed to diff {path:?}"))?;
+    let (_, additions, deletions) = patch.line_stats()?;
+    let text = patch.to_buf()?.as_str().unwrap_or_default().to_string();
+    Ok(FileDiff {
+        text,
+        additions,
+        deletions,
+    })
+}
+
+pub struct GitRepo {
+    repo: Repository,
+    path: PathBuf,
+}
+
+impl GitRepo {
+    pub fn open(path: &Path) -> Result<Self> {
+        let repo =
+            Repository::open(path).with_context(|| format!("{path:?} is not a git repository"))?;
+        if repo.is_bare() {
+            bail!("{path:?} is a bare git repository, but a working tree is needed")
+        }
+        Ok(Self {
+            repo,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn remote_url(&self) -> Result<String> {
+        let remote = self
+            .repo
+            .find_remote(REMOTE)
+            .with_context(|| format!("{:?} has no remote named \"{REMOTE}\"", self.path))?;
+        Ok(remote.url().unwrap_or_default().to_string())
+    }
+
+    fn remote_callbacks<'a>(credentials: &'a RefCell<Credentials>) -> RemoteCallbacks<'a> {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed| {
+            credentials.borrow_mut().get(url, username, allowed)
+        });
+        callbacks
+    }
+
+    fn has_ref(&self, name: &str) -> bool {
+        self.repo.find_reference(name).is_ok()
+    }
+
+    /// Detects the default branch of the remote, e.g. "origin/main".
+    ///
+    /// The branch is taken from the remote HEAD recorded on clone, then asked from the
+    /// remote itself, and only then guessed from the usual names.
+    pub fn default_branch(&self) -> Result<String> {
+        let head = format!("refs/remotes/{REMOTE}/HEAD");
+        if let Ok(head) = self.repo.find_reference(&head) {
+            if let Some(target) = head.symbolic_target() {
+                if let Some(branch) = target.strip_prefix("refs/remotes/") {
+                    return Ok(branch.to_string());
+                }
+            }
+        }
+        if let Some(branch) = self.ask_default_branch() {
+            let branch = format!("{REMOTE}/{branch}");
+            if !self.has_ref(&format!("refs/remotes/{branch}")) {
+                self.fetch(&branch[REMOTE.len() + 1..])?;
+            }
+            return Ok(branch);
+        }
+        for branch in FALLBACK_DEFAULT_BRANCHES {
+            if self.has_ref(&format!("refs/remotes/{REMOTE}/{branch}")) {
+                return Ok(format!("{REMOTE}/{branch}"));
+            }
+        }
+        bail!(
+            "cannot detect the default branch of {:?}: {REMOTE}/HEAD is not set, the remote is unreachable \
+             and neither {REMOTE}/master nor {REMOTE}/main exists; \
+             run \"git fetch {REMOTE} && git remote set-head {REMOTE} --auto\"",
+            self.path
+        )
+    }
+
+    fn ask_default_branch(&self) -> Option<String> {
+        let mut remote = self.repo.find_remote(REMOTE).ok()?;
+        let credentials = RefCell::new(Credentials::default());
+        let callbacks = Self::remote_callbacks(&credentials);
+        let connection = remote
+            .connect_auth(Direction::Fetch, Some(callbacks), None)
+            .ok()?;
+        let branch = connection.default_branch().ok()?;
+        let branch = branch.as_str()?.strip_prefix("refs/heads/")?;
+        Some(branch.to_string())
+    }
+
+    pub fn current_branch(&self) -> Result<Option<String>> {
+        let head = match self.repo.head() {
+            Ok(head) => head,
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => return Ok(None),
+            Err(err) => return Err(err).context("failed to read HEAD"),
+        };
+        Ok(head
+            .is_branch()
+            .then(|| head.shorthand().unwrap_or_default().to_string()))
+    }
+
+    pub fn local_branches(&self) -> Result<Vec<String>> {
+        let mut branches = Vec::new();
+        for branch in self.repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            if let Some(name) = branch.name()? {
+                branches.push(name.to_string());
+            }
+        }
+        Ok(branches)
+    }
+
+    /// Lists the files which differ from the last commit, untracked ones included.
+    pub fn changes(&self) -> Result<Vec<Change>> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .renames_head_to_index(true);
+        let statuses = self
+            .repo
+            .statuses(Some(&mut options))
+            .context("failed to get status of the working tree")?;
+        let mut changes = Vec::new();
+        for entry in statuses.iter() {
+            let status = entry.status();
+            let kind = if status.is_conflicted() {
+                ChangeKind::Conflicted
+            } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+                ChangeKind::Renamed
+            } else if status == Status::WT_NEW {
+                ChangeKind::Untracked
+            } else if status.intersects(Status::INDEX_NEW | Status::WT_NEW) {
+                ChangeKind::New
+            } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+                ChangeKind::Deleted
+            } else if status.is_empty() || status.is_ignored() {
+                continue;
+            } else {
+                ChangeKind::Modified
+            };
+            let path = PathBuf::from(entry.path().context("non-utf-8 path in the repository")?);
+            changes.push(Change { path, kind });
+        }
+        Ok(changes)
+    }
+
+    /// Checks whether a local branch or a branch of the remote with the name exists.
+    pub fn has_branch(&self, name: &str) -> bool {
+        self.has_ref(&format!("refs/heads/{name}"))
+            || self.has_ref(&format!("refs/remotes/{REMOTE}/{name}"))
+    }
+
+    /// Fails if the tracked files have changes, which switching to the branch would lose.
+    fn check_clean(&self, name: &str) -> Result<()> {
+        let tracked: Vec<_> = self
+            .changes()?
+            .into_iter()
+            .filter(|change| change.kind != ChangeKind::Untracked)
+            .collect();
+        if !tracked.is_empty() {
+            bail!(
+                "cannot switch {:?} to branch {name}, it has uncommitted changes: {}; \
+                 commit or stash them first",
+                self.path,
+                describe_changes(&tracked)
+            )
+        }
+        Ok(())
+    }
+
+    fn switch_to(&self, name: &str, commit: Oid) -> Result<()> {
+        let commit = self.repo.find_commit(commit)?;
+        self.repo
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .with_context(|| format!("failed to checkout branch {name}"))?;
+        self.repo
+            .set_head(&format!("refs/heads/{name}"))
+            .with_context(|| format!("failed to switch HEAD to branch {name}"))
+    }
+
+    /// Checks out the local branch, creating it from the remote one if needed.
+    pub fn checkout_branch(&self, name: &str) -> Result<()> {
+        if self.current_branch()?.as_deref() == Some(name) {
+            return Ok(());
+        }
+        // Checked before the branch is created, so that a refused switch leaves nothing behind
+        self.check_clean(name)?;
+        let commit = match self.repo.find_branch(name, BranchType::Local) {
+            Ok(branch) => branch.get().peel_to_commit()?.id(),
+            Err(_) => {
+                let remote_name = format!("{REMOTE}/{name}");
+                let remote = self
+                    .repo
+                    .find_branch(&remote_name, BranchType::Remote)
+                    .with_context(|| format!("there's no branch {name} in {:?}", self.path))?;
+                let commit = remote.get().peel_to_commit()?;
+                let mut branch = self.repo.branch(name, &commit, false)?;
+                branch.set_upstream(Some(&remote_name))?;
+                commit.id()
+            }
+        };
+        self.switch_to(name, commit)
+    }
+
+    /// Creates a branch starting at `base`, e.g. "origin/main", and checks it out.
+    pub fn checkout_new_branch(&self, name: &str, base: &str) -> Result<()> {
+        let commit = self
+            .repo
+            .revparse_single(base)
+            .with_context(|| format!("there's no {base} in {:?}", self.path))?
+            .peel_to_commit()?;
+        self.check_clean(name)?;
+        self.repo
+            .branch(name, &commit, false)
+            .with_context(|| format!("failed to create branch {name}"))?;
+        self.switch_to(name, commit.id())
+    }
+
+    /// The revision a branch with the name starts at: the local branch, the remote one
+    /// or the default branch if the branch is new.
+    pub fn branch_base(&self, name: &str) -> Result<String> {
+        if self.has_ref(&format!("refs/heads/{name}")) {
+            return Ok(name.to_string());
+        }
+        if self.has_ref(&format!("refs/remotes/{REMOTE}/{name}")) {
+            return Ok(format!("{REMOTE}/{name}"));
+        }
+        self.default_branch()
+    }
+
+    /// Checks whether the remote had the branch as of the last fetch.
+    pub fn has_remote_branch(&self, name: &str) -> bool {
+        self.has_ref(&format!("refs/remotes/{REMOTE}/{name}"))
+    }
+
+    /// The revision of the branch on the remote as of the last fetch, or the default branch
+    /// if the branch was never pushed.
+    pub fn remote_base(&self, name: &str) -> Result<String> {
+        if self.has_remote_branch(name) {
+            return Ok(format!("{REMOTE}/{name}"));
+        }
+        self.default_branch()
+    }
+
+    /// Checks whether the local branch has commits which are not on the remote.
+    pub fn has_unpushed_commits(&self, name: &str) -> Result<bool> {
+        if !self.has_ref(&format!("refs/heads/{name}")) {
+            return Ok(false);
+        }
+        Ok(match self.divergence(name)? {
+            Some((ahead, _)) => ahead > 0,
+            None => true,
+        })
+    }
+
+    /// Reads the file at the revision, `None` if the file doesn't exist there.
+    pub fn read_file(&self, revision: &str, path: &Path) -> Result<Option<Vec<u8>>> {
+        let tree = self
+            .repo
+            .revparse_single(revision)
+            .with_context(|| format!("there's no {revision} in {:?}", self.path))?
+            .peel_to_tree()?;
+        let entry = match tree.get_path(path) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+        let blob = entry
+            .to_object(&self.repo)?
+            .peel_to_blob()
+            .with_context(|| format!("{path:?} is not a file at {revision}"))?;
+        Ok(Some(blob.content().to_vec()))
+    }
+
+    /// Checks whether the files in `root` are the same as at the revision, the paths being
+    /// relative to both.
+    pub fn matches_files(&self, revision: &str, root: &Path, files: &[PathBuf]) -> Result<bool> {
+        for file in files {
+            let local = std::fs::read(root.join(file)).ok();
+            if local != self.read_file(revision, file)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stages the given paths relative to the repository root, deletions included.
+    pub fn add(&self, paths: &[PathBuf]) -> Result<()> {
+        let mut index = self.repo.index().context("failed to read the index")?;
+        for path in paths {
+            if self.path.join(path).exists() {
+                index
+                    .add_path(path)
+                    .with_context(|| format!("failed to stage {path:?}"))?;
+            } else if index.get_path(path, 0).is_some() {
+                index
+                    .remove_path(path)
+                    .with_context(|| format!("failed to stage removal of {path:?}"))?;
+            }
+        }
+        index.write().context("failed to write the index")
+    }
+
+    pub fn commit(&self, message: &str) -> Result<Oid> {
+        let mut index = self.repo.index().context("failed to read the index")?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err).context("failed to read HEAD"),
+        };
+        if parent
+            .as_ref()
+            .is_some_and(|parent| parent.tree_id() == tree.id())
+        {
+            bail!("nothing to commit, the files are the same as in the last commit")
+        }
+        let signature = self.repo.signature().context(
+            "git user is not configured, set it with \"git config --global user.name NAME\" \
+             and \"git config --global user.email EMAIL\"",
+        )?;
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .context("failed to commit")
+    }
+
+    pub fn fetch(&self, branch: &str) -> Result<()> {
+        let mut remote = self
+            .repo
+            .find_remote(REMOTE)
+            .with_context(|| format!("{:?} has no remote named \"{REMOTE}\"", self.path))?;
+        let credentials = RefCell::new(Credentials::default());
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(Self::remote_callbacks(&credentials));
+        let refspec = format!("+refs/heads/{branch}:refs/remotes/{REMOTE}/{branch}");
+        remote
+            .fetch(&[refspec], Some(&mut options), None)
+            .with_context(|| format!("failed to fetch {branch} from {REMOTE}"))
+    }
+
+    /// Returns the number of commits only in the local branch and only in the remote one,
+    /// or `None` if the remote has no such branch.
+    pub fn divergence(&self, branch: &str) -> Result<Option<(usize, usize)>> {
+        let local = self
+            .repo
+            .find_branch(branch, BranchType::Local)
+            .with_context(|| format!("there's no branch {branch}"))?
+            .get()
+            .peel_to_commit()?
+            .id();
+        let remote = match self
+            .repo
+            .find_branch(&format!("{REMOTE}/{branch}"), BranchType::Remote)
+        {
+            Ok(remote) => remote.get().peel_to_commit()?.id(),
+            Err(_) => return Ok(None),
+        };
+        Ok(Some(self.repo.graph_ahead_behind(local, remote)?))
+    }
+
+    /// Pushes the branch and sets it as upstream, refusing if the remote one has other commits.
+    pub fn push(&self, branch: &str) -> Result<()> {
+        self.fetch(branch)?;
+        if let Some((ahead, behind)) = self.divergence(branch)? {
+            if behind > 0 && ahead > 0 {
+                bail!(
+                    "branch {branch} has diverged from {REMOTE}/{branch}: {ahead} local and {behind} remote \
+                     commits differ; run \"git pull\" in {:?} to merge them first",
+                    self.path
+                )
+            }
+            if behind > 0 {
+                bail!(
+                    "branch {branch} is {behind} commits behind {REMOTE}/{branch}; \
+                     run \"git pull\" in {:?} first",
+                    self.path
+                )
+            }
+        }
+        let mut remote = self.repo.find_remote(REMOTE)?;
+        let credentials = RefCell::new(Credentials::default());
+        let rejection = RefCell::new(None);
+        let mut callbacks = Self::remote_callbacks(&credentials);
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(status) = status {
+                *rejection.borrow_mut() = Some(format!("{reference}: {status}"));
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+        remote
+            .push(&[refspec], Some(&mut options))
+            .with_context(|| format!("failed to push {branch} to {REMOTE}"))?;
+        if let Some(rejection) = rejection.borrow().as_ref() {
+            bail!("{REMOTE} rejected the push of {rejection}")
+        }
+        self.repo
+            .find_branch(branch, BranchType::Local)?
+            .set_upstream(Some(&format!("{REMOTE}/{branch}")))
+            .with_context(|| format!("failed to set upstream of {branch}"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{ChangeKind, GitRepo};
+    use git2::{Repository, Signature};
+    use std::{fs, path::Path};
+    use tempfile::TempDir;
+
+    fn configure(repo: &Repository) {
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Student").unwrap();
+        config.set_str("user.email", "student@example.com").unwrap();
+    }
+
+    /// Creates a bare "origin" with a single commit on `default_branch`.
+    pub(crate) fn origin(root: &Path, default_branch: &str) -> String {
+        let origin = root.join("origin.git");
+        let repo = Repository::init_bare(&origin).unwrap();
+        let signature = Signature::now("Teacher", "teacher@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let reference = format!("refs/heads/{default_branch}");
+        repo.commit(Some(&reference), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.set_head(&reference).unwrap();
+        origin.to_str().unwrap().to_string()
+    }
+
+    pub(crate) fn clone(url: &str, path: &Path) -> GitRepo {
+        configure(&Repository::clone(url, path).unwrap());
+        GitRepo::open(path).unwrap()
+    }
+
+    #[test]
+    fn detects_default_branch_from_remote() {
+        let root = TempDir::new().unwrap();
+        let url = origin(root.path(), "trunk");
+        let repo = clone(&url, &root.path().join("work"));
+        assert_eq!(repo.default_branch().unwrap(), "origin/trunk");
+        repo.repo
+            .find_reference("refs/remotes/origin/HEAD")
+            .unwrap()
+            .delete()
+            .unwrap();
+        assert_eq!(repo.default_branch().unwrap(), "origin/trunk");
+    }
+
+    #[test]
+    fn reports_changes() {
+        let root = TempDir::new().unwrap();
+        let url = origin(root.path(), "main");
+        let work = root.path().join("work");
+        let repo = clone(&url, &work);
+        fs::write(work.join("a.rs"), "fn main() {}").unwrap();
+        let changes = repo.changes().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Untracked);
+        assert_eq!(changes[0].to_string(), "a.rs (untracked)");
+        repo.add(&["a.rs".into()]).unwrap();
+        repo.commit("add a.rs").unwrap();
+        assert!(repo.changes().unwrap().is_empty());
+        fs::write(work.join("a.rs"), "fn main() { todo!() }").unwrap();
+        assert_eq!(repo.changes().unwrap()[0].kind, ChangeKind::Modified);
+        let err = repo.checkout_new_branch("x/y", "origin/main").unwrap_err();
+        assert!(format!("{err:#}").contains("a.rs (modified)"), "{err:#}");
+        assert!(!repo.has_ref("refs/heads/x/y"));
+    }
+
+    #[test]
+    fn commits_only_unborn_branch_without_parent() {
+        let root = TempDir::new().unwrap();
+        let work = root.path().join("work");
+        configure(&Repository::init(&work).unwrap());
+        let repo = GitRepo::open(&work).unwrap();
+        fs::write(work.join("a.rs"), "").unwrap();
+        repo.add(&["a.rs".into()]).unwrap();
+        let first = repo.commit("first").unwrap();
+        assert_eq!(repo.repo.find_commit(first).unwrap().parent_count(), 0);
+
+        let branch = repo.current_branch().unwrap().unwrap();
+        fs::write(work.join(".git/refs/heads").join(&branch), "broken\n").unwrap();
+        fs::write(work.join("a.rs"), "changed").unwrap();
+        repo.add(&["a.rs".into()]).unwrap();
+        let err = repo.commit("second").unwrap_err();
+        assert!(err.to_string().contains("failed to read HEAD"), "{err}");
+    }
+
+    #[test]
+    fn commits_and_pushes_new_branch() {
+        let root = TempDir::new().unwrap();
+        let url = origin(root.path(), "main");
+        let work = root.path().join("work");
+        let repo = clone(&url, &work);
+        let base = repo.default_branch().unwrap();
+        repo.checkout_new_branch("tutorial/add", &base).unwrap();
+        assert_eq!(
+            repo.current_branch().unwrap().as_deref(),
+            Some("tutorial/add")
+        );
+        fs::write(work.join("lib.rs"), "").unwrap();
+        fs::write(work.join("unrelated.rs"), "").unwrap();
+        repo.add(&["lib.rs".into()]).unwrap();
+        repo.commit("solution").unwrap();
+        assert_eq!(repo.changes().unwrap().len(), 1);
+        let err = repo.commit("again").unwrap_err();
+        assert!(err.to_string().contains("nothing to commit"), "{err}");
+        repo.push("tutorial/add").unwrap();
+        assert_eq!(repo.divergence("tutorial/add").unwrap(), Some((0, 0)));
+        let origin = Repository::open_bare(&url).unwrap();
+        assert!(origin.find_reference("refs/heads/tutorial/add").is_ok());
+
+        let other_path = root.path().join("other");
+        let other = clone(&url, &other_path);
+        assert!(other.has_branch("tutorial/add"));
+        fs::write(other_path.join("notes.md"), "").unwrap();
+        other.add(&["notes.md".into()]).unwrap();
+        other.commit("notes").unwrap();
+        fs::write(other_path.join("notes.md"), "draft").unwrap();
+        let err = other.checkout_branch("tutorial/add").unwrap_err();
+        assert!(err.to_string().contains("notes.md (modified)"), "{err}");
+        assert!(!other.has_ref("refs/heads/tutorial/add"));
+        fs::write(other_path.join("notes.md"), "").unwrap();
+        other.checkout_branch("tutorial/add").unwrap();
+        assert!(root.path().join("other/lib.rs").is_file());
+    }
+
+    #[test]
+    fn refuses_to_push_diverged_branch() {
+        let root = TempDir::new().unwrap();
+        let url = origin(root.path(), "main");
+        let first = clone(&url, &root.path().join("first"));
+        let second = clone(&url, &root.path().join("second"));
+        for (repo, dir) in [(&first, "first"), (&second, "second")] {
+            repo.checkout_branch("main").unwrap();
+            fs::write(root.path().join(dir).join("lib.rs"), dir).unwrap();
+            repo.add(&["lib.rs".into()]).unwrap();
+            repo.commit(dir).unwrap();
+        }
+        first.push("main").unwrap();
+        let err = second.push("main").unwrap_err();
+        assert!(err.to_string().contains("diverged"), "{err}");
+    }
+
+    #[test]
+    fn keeps_unpushed_commits_after_failed_push() {
+        let root = TempDir::new().unwrap();
+        let url = origin(root.path(), "main");
+        let work = root.path().join("work");
+        let repo = clone(&url, &work);
+        repo.checkout_new_branch("tutorial/add", "origin/main")
+            .unwrap();
+        fs::write(work.join("lib.rs"), "fn add() {}").unwrap();
+        repo.add(&["lib.rs".into()]).unwrap();
+        repo.commit("solution").unwrap();
+        let files = ["lib.rs".into()];
+        assert!(repo.matches_files("tutorial/add", &work, &files).unwrap());
+        assert!(!repo.matches_files("origin/main", &work, &files).unwrap());
+
+        repo.repo.remote_set_url("origin", "/nonexistent").unwrap();
+        assert!(repo.push("tutorial/add").is_err());
+        assert_eq!(repo.branch_base("tutorial/add").unwrap(), "tutorial/add");
+        assert_eq!(repo.remote_base("tutorial/add").unwrap(), "origin/main");
+        assert!(repo.has_unpushed_commits("tutorial/add").unwrap());
+
+        repo.repo.remote_set_url("origin", &url).unwrap();
+        repo.push("tutorial/add").unwrap();
+        assert_eq!(
+            repo.remote_base("tutorial/add").unwrap(),
+            "origin/tutorial/add"
+        );
+        assert!(!repo.has_unpushed_commits("tutorial/add").unwrap());
+        assert!(!repo.has_unpushed_commits("tutorial/sub").unwrap());
+    }
+}
@@ -0,0 +1,2 @@
+mod credentials;
+pub mod git_repo;
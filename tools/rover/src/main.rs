@@ -1,13 +1,33 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{Arg, Command};
-use compose::run_compose::run_compose;
+use compose::run_compose::{run_compose, ComposeOptions};
+use config::check::check_configs;
+use doctor::run_doctor::run_doctor;
+use repository::context::LaunchOptions;
 use repository::repo::Repository;
-use std::path::PathBuf;
-use submitting::submit::submit_problem;
-use testing::{report::ReportType, test::test_problem};
+use scaffold::run_new::{run_new, Template};
+use similarity::run_similarity::{run_similarity, SimilarityOptions};
+use status::run_status::run_status;
+use std::{path::PathBuf, thread};
+use submitting::submit::{submit_problem, SubmitOptions};
+use testing::{
+    all::{print_table, test_all_problems},
+    junit::write_junit,
+    report::{reporter_from_name, Report, ReportOptions},
+    result::write_json,
+    test::test_problem,
+    watch::watch_problem,
+};
+use util::duration::parse_duration;
 
 mod compose;
+mod config;
+mod doctor;
+mod git;
 mod repository;
+mod scaffold;
+mod similarity;
+mod status;
 mod submitting;
 mod testing;
 mod util;
@@ -42,6 +62,29 @@ fn main() -> Result<()> {
                         .required(false)
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show the files to submit and their diff without copying or committing them")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("no-verify")
+                        .long("no-verify")
+                        .help("Submit without running the testing steps locally first")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("verify-step")
+                        .long("verify-step")
+                        .help("Step of \".config.yml\" to run before submitting, all of them by default")
+                        .required(false)
+                        .conflicts_with("no-verify")
+                        .multiple_occurrences(true)
+                        .takes_value(true)
+                )
         )
         .subcommand(
             Command::new("test")
@@ -77,6 +120,122 @@ fn main() -> Result<()> {
                         .help("Set system that will accept the results of testing")
                         .required(false)
                         .default_value("no-report")
+                        .possible_values(["no-report", "manytask", "webhook", "file", "github"])
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("report-url")
+                        .long("report-url")
+                        .help("URL to post the report to with the webhook or manytask report")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("report-header")
+                        .long("report-header")
+                        .help("Header of the webhook report request in \"NAME: VALUE\" form")
+                        .required(false)
+                        .multiple_occurrences(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("report-field")
+                        .long("report-field")
+                        .help("Extra field of the webhook report body in \"KEY=VALUE\" form")
+                        .required(false)
+                        .multiple_occurrences(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("report-file")
+                        .long("report-file")
+                        .help("Path to the file the file report appends to")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Test every problem of the course repository")
+                        .required(false)
+                        .conflicts_with_all(&[
+                            "move-files",
+                            "report-to",
+                            "report-url",
+                            "report-header",
+                            "report-field",
+                            "report-file",
+                        ])
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("watch")
+                        .long("watch")
+                        .help("Rerun the steps whenever the solution files change, until Ctrl+C")
+                        .required(false)
+                        .conflicts_with_all(&[
+                            "all",
+                            "move-files",
+                            "report-to",
+                            "report-url",
+                            "report-header",
+                            "report-field",
+                            "report-file",
+                            "json-report",
+                            "junit-report",
+                        ])
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("Number of problems tested at once with --all")
+                        .required(false)
+                        .requires("all")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Test the problems with --all even if they are unchanged since the last successful run")
+                        .required(false)
+                        .requires("all")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .help("Wall-clock timeout of each command without its own, e.g. \"90s\" or \"10m\"")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("fail-fast")
+                        .long("fail-fast")
+                        .help("Skip the remaining commands after the first failed one")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("sandbox")
+                        .long("sandbox")
+                        .help("Run the commands with the course repository read-only, loopback-only network and a clean environment (Linux only)")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("json-report")
+                        .long("json-report")
+                        .help("Path to write the per-step testing results as JSON")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("junit-report")
+                        .long("junit-report")
+                        .help("Path to write the per-step testing results as JUnit XML")
+                        .required(false)
                         .takes_value(true)
                 )
         )
@@ -98,6 +257,164 @@ fn main() -> Result<()> {
                         .required(true)
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::new("feature")
+                        .long("feature")
+                        .help("Feature enabling the conditional compose markers, in addition to the \"features\" of \".compose.yml\"")
+                        .required(false)
+                        .multiple_occurrences(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Run \"cargo check\" on the composed workspace")
+                        .required(false)
+                        .conflicts_with("plan")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .help("Print the files to create, modify and delete in the output without touching it")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("incremental")
+                        .long("incremental")
+                        .help("Only write the changed files, keeping the unchanged ones untouched")
+                        .required(false)
+                        .takes_value(false)
+                )
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check that the environment is ready for solving the problems")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path to the course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("solutions-repo")
+                        .long("solutions-repo")
+                        .help("Path to the solutions repository")
+                        .required(false)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Show the submission state of every problem")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path to the course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("solutions-repo")
+                        .long("solutions-repo")
+                        .help("Path to the solutions repository")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("deadlines")
+                        .long("deadlines")
+                        .help("Path to the deadlines file, \".deadlines.yml\" of the course repository by default")
+                        .required(false)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Work with testing configuration files of the problems")
+                .subcommand(
+                    Command::new("check")
+                        .about("Validate \".config.yml\" of every problem in the course repository")
+                        .arg(
+                            Arg::new("path")
+                                .long("path")
+                                .help("Path to the course repository")
+                                .required(false)
+                                .default_value(".")
+                                .hide_default_value(true)
+                                .takes_value(true)
+                        )
+                )
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Create a new problem from a template")
+                .arg(
+                    Arg::new("name")
+                        .help("Name of the problem in GROUP/TITLE form")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("kind")
+                        .long("kind")
+                        .help("Template of the problem")
+                        .required(false)
+                        .possible_values(["lib", "bin", "proc-macro"])
+                        .default_value("lib")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path to the course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("similarity")
+                .about("Find similar solutions of the problems to spot copying")
+                .arg(
+                    Arg::new("solutions")
+                        .help("Directory with the solutions repository of every student")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("problem")
+                        .long("problem")
+                        .help("Compare only the solutions of the problem in GROUP/TITLE form")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("min-similarity")
+                        .long("min-similarity")
+                        .help("Minimal percentage of the shared fingerprints of the reported pairs")
+                        .required(false)
+                        .default_value("50")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path to the course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
         )
         .arg_required_else_help(true)
         .get_matches();
@@ -107,27 +424,150 @@ fn main() -> Result<()> {
             let path: PathBuf = submit_matches.value_of("path").unwrap().into();
             let message = submit_matches.value_of("message").unwrap();
             let solutions_repo = submit_matches.value_of("solutions-repo").map(PathBuf::from);
-            submit_problem(&path, message, solutions_repo)
+            let options = SubmitOptions {
+                dry_run: submit_matches.is_present("dry-run"),
+                verify: !submit_matches.is_present("no-verify"),
+                verify_steps: submit_matches
+                    .values_of("verify-step")
+                    .into_iter()
+                    .flatten()
+                    .map(String::from)
+                    .collect(),
+            };
+            submit_problem(&path, message, solutions_repo, &options)
         }
         Some(("test", test_matches)) => {
             let path: PathBuf = test_matches.value_of("path").unwrap().into();
             let repository = Repository::from_path(&path)?;
+            let options = LaunchOptions {
+                fail_fast: test_matches.is_present("fail-fast"),
+                timeout: test_matches
+                    .value_of("timeout")
+                    .map(parse_duration)
+                    .transpose()?,
+                sandbox: test_matches
+                    .is_present("sandbox")
+                    .then(|| repository.sandbox()),
+                ..Default::default()
+            };
+            if test_matches.is_present("all") {
+                let jobs = match test_matches.value_of("jobs") {
+                    Some(jobs) => jobs.parse().context("jobs is not a number")?,
+                    None => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+                };
+                let use_cache = !test_matches.is_present("force");
+                let runs = test_all_problems(&repository, jobs, use_cache, &options)?;
+                print_table(&runs);
+                let results: Vec<_> = runs.iter().filter_map(|run| run.result.as_ref()).collect();
+                if let Some(path) = test_matches.value_of("json-report") {
+                    write_json(&results, &PathBuf::from(path))?;
+                }
+                if let Some(path) = test_matches.value_of("junit-report") {
+                    write_junit(&results, &PathBuf::from(path))?;
+                }
+                let failed = runs.iter().filter(|run| !run.is_ok()).count();
+                if failed > 0 {
+                    bail!("testing failed for {failed} problems")
+                }
+                return Ok(());
+            }
             let problem = repository.problem_from_path(&path)?;
-            let report = ReportType::from_name(test_matches.value_of("report-to").unwrap())?;
+            if test_matches.is_present("watch") {
+                return watch_problem(problem, &options);
+            }
+            let report_options = ReportOptions {
+                url: test_matches.value_of("report-url").map(String::from),
+                headers: test_matches
+                    .values_of("report-header")
+                    .into_iter()
+                    .flatten()
+                    .map(ReportOptions::parse_header)
+                    .collect::<Result<_>>()?,
+                fields: test_matches
+                    .values_of("report-field")
+                    .into_iter()
+                    .flatten()
+                    .map(ReportOptions::parse_field)
+                    .collect::<Result<_>>()?,
+                file: test_matches.value_of("report-file").map(PathBuf::from),
+            };
+            let reporter =
+                reporter_from_name(test_matches.value_of("report-to").unwrap(), report_options)?;
             if let Some(solutions_repo) = test_matches.value_of("move-files") {
                 let checkout_branch = test_matches.value_of("checkout-branch").is_some();
                 let solutions_repo: PathBuf = solutions_repo.into();
                 problem.move_solution_files_from(&solutions_repo, checkout_branch)?;
             }
-            // TODO: Make testing errors more clear
-            let testing_result = test_problem(problem);
-            let report_push = report.push_report(testing_result.is_err());
-            testing_result.and(report_push)
+            let result = test_problem(&problem, &options);
+            let report = Report::new(
+                problem.branch_name(),
+                result.as_ref().map_or(true, |r| r.failed),
+                result.as_ref().ok(),
+            );
+            let report_push = reporter.push_report(&report);
+            let result = result?;
+            if let Some(path) = test_matches.value_of("json-report") {
+                write_json(&result, &PathBuf::from(path))?;
+            }
+            if let Some(path) = test_matches.value_of("junit-report") {
+                write_junit(&[&result], &PathBuf::from(path))?;
+            }
+            if result.failed {
+                bail!("testing failed: {}", result.failure_summary())
+            }
+            report_push
         }
         Some(("compose", compose_matches)) => {
             let input: PathBuf = compose_matches.value_of("input").unwrap().into();
             let output: PathBuf = compose_matches.value_of("output").unwrap().into();
-            run_compose(&input, &output)
+            let options = ComposeOptions {
+                features: compose_matches
+                    .values_of("feature")
+                    .map(|values| values.map(String::from).collect())
+                    .unwrap_or_default(),
+                check: compose_matches.is_present("check"),
+                plan: compose_matches.is_present("plan"),
+                incremental: compose_matches.is_present("incremental"),
+            };
+            run_compose(&input, &output, &options)
+        }
+        Some(("doctor", doctor_matches)) => {
+            let path: PathBuf = doctor_matches.value_of("path").unwrap().into();
+            let solutions_repo = doctor_matches.value_of("solutions-repo").map(PathBuf::from);
+            run_doctor(&path, solutions_repo)
+        }
+        Some(("status", status_matches)) => {
+            let path: PathBuf = status_matches.value_of("path").unwrap().into();
+            let solutions_repo = status_matches.value_of("solutions-repo").map(PathBuf::from);
+            let deadlines = status_matches.value_of("deadlines").map(PathBuf::from);
+            run_status(&path, solutions_repo, deadlines)
+        }
+        Some(("config", config_matches)) => match config_matches.subcommand() {
+            Some(("check", check_matches)) => {
+                let path: PathBuf = check_matches.value_of("path").unwrap().into();
+                check_configs(&path)
+            }
+            _ => unreachable!(),
+        },
+        Some(("new", new_matches)) => {
+            let path: PathBuf = new_matches.value_of("path").unwrap().into();
+            let name = new_matches.value_of("name").unwrap();
+            let template = Template::from_name(new_matches.value_of("kind").unwrap())?;
+            run_new(&path, name, template)
+        }
+        Some(("similarity", similarity_matches)) => {
+            let path: PathBuf = similarity_matches.value_of("path").unwrap().into();
+            let solutions: PathBuf = similarity_matches.value_of("solutions").unwrap().into();
+            let min_similarity: f64 = similarity_matches
+                .value_of("min-similarity")
+                .unwrap()
+                .parse()
+                .context("min-similarity is not a number")?;
+            let options = SimilarityOptions {
+                min_similarity: min_similarity / 100.0,
+                problem: similarity_matches.value_of("problem").map(String::from),
+            };
+            run_similarity(&path, &solutions, &options)
         }
         _ => unreachable!(),
     }
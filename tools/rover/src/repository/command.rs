@@ -1,19 +1,129 @@
+use crate::util::duration::deserialize_duration;
 use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Component, PathBuf},
+    time::Duration,
+};
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expectation {
+    #[default]
+    Success,
+    Failure,
+}
+
+#[derive(Debug)]
+pub struct CustomCommand {
+    name: Option<String>,
+    program: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+    expect: Expectation,
+}
+
+impl CustomCommand {
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[String] {
+        self.args.as_slice()
+    }
+
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn cwd(&self) -> Option<&PathBuf> {
+        self.cwd.as_ref()
+    }
+
+    pub fn expect(&self) -> Expectation {
+        self.expect
+    }
+
+    /// The working directory must stay inside the problem directory.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(cwd) = &self.cwd {
+            if cwd
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                bail!("cwd {cwd:?} must be a relative path inside the problem directory")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A command written as a mapping: a built-in one with the `builtin` key to set its
+/// options, or a custom one. Both kinds are read at once, so that the errors point
+/// at the wrong keys.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CommandEntry {
+    builtin: Option<String>,
+    name: Option<String>,
+    program: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+    expect: Option<Expectation>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    timeout: Option<Duration>,
+}
+
+impl CommandEntry {
+    /// Returns the command along with its timeout.
+    pub fn into_command(self) -> Result<(Command, Option<Duration>)> {
+        match (self.builtin, self.program) {
+            (Some(builtin), None) => {
+                let is_custom = self.name.is_some()
+                    || !self.args.is_empty()
+                    || !self.env.is_empty()
+                    || self.cwd.is_some()
+                    || self.expect.is_some();
+                if is_custom {
+                    bail!("built-in command \"{builtin}\" can only have a timeout")
+                }
+                Ok((Command::from_name(&builtin)?, self.timeout))
+            }
+            (None, Some(program)) => {
+                let custom = CustomCommand {
+                    name: self.name,
+                    program,
+                    args: self.args,
+                    env: self.env,
+                    cwd: self.cwd,
+                    expect: self.expect.unwrap_or_default(),
+                };
+                Ok((Command::Custom(custom), self.timeout))
+            }
+            (Some(_), Some(_)) => bail!("command can't have both \"builtin\" and \"program\""),
+            (None, None) => bail!("command must have either \"builtin\" or \"program\""),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum Command {
     ForbidUnsafe,
     ForbidCollections,
     ForbidStd,
+    ForbidApi,
     CargoFmt,
     CargoClippy,
     CargoTest,
     CargoTestDebug,
     CargoMiriTest,
-    CargoCompileTestMiniFrunk,
-    CargoCompileTestOrm,
-    CargoCompileTestSnapshot,
     PythonTest,
+    Custom(CustomCommand),
 }
 
 impl Command {
@@ -22,33 +132,138 @@ impl Command {
             "forbid-unsafe" => Self::ForbidUnsafe,
             "forbid-collections" => Self::ForbidCollections,
             "forbid-std" => Self::ForbidStd,
+            "forbid-api" => Self::ForbidApi,
             "cargo-fmt" => Self::CargoFmt,
             "cargo-clippy" => Self::CargoClippy,
             "cargo-test" => Self::CargoTest,
             "cargo-test-debug" => Self::CargoTestDebug,
             "cargo-miri-test" => Self::CargoMiriTest,
-            "cargo-compile-test-mini-frunk" => Self::CargoCompileTestMiniFrunk,
-            "cargo-compile-test-orm" => Self::CargoCompileTestOrm,
-            "cargo-compile-test-snapshot" => Self::CargoCompileTestSnapshot,
             "python-test" => Self::PythonTest,
             name => bail!("command \"{name}\" is not supported"),
         })
     }
 
+    pub fn name(&self) -> &str {
+        match self {
+            Self::ForbidUnsafe => "forbid-unsafe",
+            Self::ForbidCollections => "forbid-collections",
+            Self::ForbidStd => "forbid-std",
+            Self::ForbidApi => "forbid-api",
+            Self::CargoFmt => "cargo-fmt",
+            Self::CargoClippy => "cargo-clippy",
+            Self::CargoTest => "cargo-test",
+            Self::CargoTestDebug => "cargo-test-debug",
+            Self::CargoMiriTest => "cargo-miri-test",
+            Self::PythonTest => "python-test",
+            Self::Custom(custom) => custom.name.as_deref().unwrap_or(&custom.program),
+        }
+    }
+
+    /// The rustup components the command needs in its toolchain.
+    pub fn required_components(&self) -> &'static [&'static str] {
+        match self {
+            Self::CargoFmt => &["rustfmt"],
+            Self::CargoClippy => &["clippy"],
+            Self::CargoMiriTest => &["miri"],
+            _ => &[],
+        }
+    }
+
+    /// The program the command launches, if any.
+    pub fn required_program(&self) -> Option<&str> {
+        match self {
+            Self::ForbidUnsafe | Self::ForbidCollections | Self::ForbidStd | Self::ForbidApi => {
+                None
+            }
+            Self::CargoFmt
+            | Self::CargoClippy
+            | Self::CargoTest
+            | Self::CargoTestDebug
+            | Self::CargoMiriTest => Some("cargo"),
+            Self::PythonTest => Some("python3"),
+            Self::Custom(custom) => Some(&custom.program),
+        }
+    }
+
     pub fn get_shell_line(&self) -> Result<String> {
         Ok(match self {
             Self::ForbidUnsafe => bail!("no shell line for ForbidUnsafe"),
             Self::ForbidCollections => bail!("no shell line for ForbidCollections"),
             Self::ForbidStd => bail!("no shell line for ForbidStd"),
+            Self::ForbidApi => bail!("no shell line for ForbidApi"),
             Self::CargoFmt => "cargo fmt --check".to_string(),
             Self::CargoClippy => "cargo clippy --release -- -D warnings".to_string(),
             Self::CargoTest => "cargo test --release".to_string(),
             Self::CargoTestDebug => "cargo test".to_string(),
             Self::CargoMiriTest => "cargo miri test --release".to_string(),
-            Self::CargoCompileTestMiniFrunk => bail!("no shell line for CargoCompileTestMiniFrunk"),
-            Self::CargoCompileTestOrm => bail!("no shell line for CargoCompileTestOrm"),
-            Self::CargoCompileTestSnapshot => bail!("no shell line for CargoCompileTestSnapshot"),
             Self::PythonTest => "python3 test.py".to_string(),
+            Self::Custom(custom) => std::iter::once(&custom.program)
+                .chain(custom.args.iter())
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::step::StepCommand;
+
+    fn parse(yml: &str) -> Result<StepCommand, serde_yaml::Error> {
+        serde_yaml::from_str(yml)
+    }
+
+    #[test]
+    fn custom_commands() {
+        let entry = parse(
+            "program: python3\nargs: [check.py, --strict]\nenv: {MODE: full}\n\
+             cwd: tests\nexpect: failure\ntimeout: 2m\n",
+        )
+        .unwrap();
+        assert_eq!(entry.timeout, Some(Duration::from_secs(120)));
+        let custom = match &entry.command {
+            Command::Custom(custom) => custom,
+            command => panic!("{command:?} is not custom"),
+        };
+        assert_eq!(entry.command.name(), "python3");
+        assert_eq!(
+            entry.command.get_shell_line().unwrap(),
+            "python3 check.py --strict"
+        );
+        assert_eq!(custom.env()["MODE"], "full");
+        assert_eq!(custom.cwd(), Some(&PathBuf::from("tests")));
+        assert_eq!(custom.expect(), Expectation::Failure);
+        custom.validate().unwrap();
+
+        let entry = parse("{name: lint, program: ./lint.sh}").unwrap();
+        assert_eq!(entry.command.name(), "lint");
+        assert!(matches!(
+            parse("{builtin: cargo-test, timeout: 10s}")
+                .unwrap()
+                .command,
+            Command::CargoTest
+        ));
+
+        for yml in [
+            "{args: [x]}",
+            "{program: x, shell: true}",
+            "{program: x, expect: maybe}",
+            "{builtin: cargo-bench}",
+        ] {
+            assert!(parse(yml).is_err(), "{yml} is accepted");
+        }
+    }
+
+    #[test]
+    fn cwd_stays_inside_problem() {
+        for cwd in ["/tmp", "..", "tests/../..", "tests/.."] {
+            let entry = parse(&format!("{{program: x, cwd: \"{cwd}\"}}")).unwrap();
+            match entry.command {
+                Command::Custom(custom) => assert!(custom.validate().is_err(), "{cwd} is accepted"),
+                command => panic!("{command:?} is not custom"),
+            }
+        }
+    }
+}
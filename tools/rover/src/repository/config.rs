@@ -1,14 +1,62 @@
-#![allow(clippy::needless_question_mark)]
-
-use super::{command::Command, context::CommandContext, step::Step, toolchain::Toolchain};
-use anyhow::{Context, Result};
+use super::{
+    command::Command,
+    context::{CommandContext, LaunchOptions},
+    limits::Limits,
+    policy::ForbidRule,
+    scoring::Scoring,
+    step::{deserialize_steps, Step},
+    toolchain::Toolchain,
+};
+use anyhow::{bail, Context, Result};
 use glob::{glob_with, MatchOptions};
-use serde_yaml::{from_reader, Value};
+use serde::{de, Deserialize, Deserializer};
 use std::{
-    fs::File,
+    fs,
     path::{Path, PathBuf},
 };
 
+/// The latest version of the `.config.yml` schema.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The `.config.yml` as it is written by the problem authors.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Schema {
+    #[serde(default = "default_version", deserialize_with = "deserialize_version")]
+    #[allow(dead_code)]
+    version: u32,
+    #[serde(default = "default_toolchain")]
+    toolchain: Toolchain,
+    #[serde(default)]
+    allowed_patterns: Vec<String>,
+    #[serde(deserialize_with = "deserialize_steps")]
+    steps: Vec<Step>,
+    #[serde(default)]
+    forbid: Vec<ForbidRule>,
+    #[serde(default)]
+    limits: Limits,
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_toolchain() -> Toolchain {
+    Toolchain::Stable
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version == 0 || version > CONFIG_VERSION {
+        return Err(de::Error::custom(format!(
+            "config version {version} is not supported, the latest one is {CONFIG_VERSION}"
+        )));
+    }
+    Ok(version)
+}
+
 #[derive(Debug)]
 pub struct Config {
     workdir: PathBuf,
@@ -16,51 +64,53 @@ pub struct Config {
     relative_user_files: Vec<PathBuf>,
     absolute_user_files: Vec<PathBuf>,
     steps: Vec<Step>,
+    forbid: Vec<ForbidRule>,
+    limits: Limits,
+    scoring: Scoring,
 }
 
 impl Config {
     pub fn from_yml(path: &Path) -> Result<Self> {
-        let file = File::open(path).context("no yml file with config")?;
-        let yml: Value = from_reader(file).context("unable to read yml file with config")?;
-        let yml = yml.as_mapping().context("the yml is not a mapping")?;
+        let text = fs::read_to_string(path).context("no yml file with config")?;
+        let schema: Schema =
+            serde_yaml::from_str(&text).with_context(|| format!("invalid config {path:?}"))?;
         let workdir = path.parent().context("yml has no parent")?.to_path_buf();
-        let toolchain = Toolchain::from_name(
-            yml[&Value::String("toolchain".to_string())]
-                .as_str()
-                .context("toolchain is not a string")?,
-        )?;
-        let patterns = yml[&Value::String("allowed-patterns".to_string())]
-            .as_sequence()
-            .context("user files are not in a list")?
-            .iter()
-            .map(|value| Ok(value.as_str().context("user file path is not a string")?))
-            .collect::<Result<Vec<_>>>()?;
         let (relative_user_files, absolute_user_files) =
-            Self::get_matching_user_files(&workdir, patterns.as_slice())?;
-        let steps = yml[&Value::String("steps".to_string())]
-            .as_mapping()
-            .context("steps is not a mapping")?
-            .iter()
-            .map(|(key, value)| {
-                let name = key
-                    .as_str()
-                    .context("name of the step is not a string")?
-                    .to_string();
-                let commands = value
-                    .as_sequence()
-                    .context("the step commands are not a sequence")?
-                    .iter()
-                    .map(|value| Command::from_name(value.as_str().unwrap()).unwrap())
-                    .collect();
-                Ok(Step::new(name, commands))
-            })
-            .collect::<Result<Vec<_>>>()?;
+            Self::get_matching_user_files(&workdir, &schema.allowed_patterns)?;
+        for (i, rule) in schema.forbid.iter().enumerate() {
+            rule.validate()
+                .with_context(|| format!("invalid forbid rule #{} in {path:?}", i + 1))?;
+        }
+        for step in &schema.steps {
+            for entry in step.commands() {
+                if let Command::Custom(custom) = &entry.command {
+                    custom.validate().with_context(|| {
+                        format!("invalid command \"{}\" in {path:?}", entry.command.name())
+                    })?;
+                }
+            }
+        }
+        schema
+            .scoring
+            .validate(&schema.steps)
+            .with_context(|| format!("invalid scoring in {path:?}"))?;
+        let runs_forbid_api = schema.steps.iter().any(|step| {
+            step.commands()
+                .iter()
+                .any(|entry| matches!(entry.command, Command::ForbidApi))
+        });
+        if !schema.forbid.is_empty() && !runs_forbid_api {
+            bail!("forbid section is set in {path:?}, but no step runs \"forbid-api\"")
+        }
         Ok(Self {
             workdir,
-            toolchain,
+            toolchain: schema.toolchain,
             relative_user_files,
             absolute_user_files,
-            steps,
+            steps: schema.steps,
+            forbid: schema.forbid,
+            limits: schema.limits,
+            scoring: schema.scoring,
         })
     }
 
@@ -72,22 +122,31 @@ impl Config {
         &self.toolchain
     }
 
+    pub fn get_scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
     pub fn get_relative_user_files(&self) -> &[PathBuf] {
         self.relative_user_files.as_slice()
     }
 
-    #[allow(dead_code)]
     pub fn get_absolute_user_files(&self) -> &[PathBuf] {
         self.absolute_user_files.as_slice()
     }
 
-    pub fn get_command_context(&self) -> CommandContext {
-        CommandContext::new(&self.workdir, self.absolute_user_files.as_slice())
+    pub fn get_command_context(&self, options: &LaunchOptions) -> CommandContext {
+        CommandContext::new(
+            &self.workdir,
+            self.absolute_user_files.as_slice(),
+            self.forbid.as_slice(),
+            self.limits,
+            options,
+        )
     }
 
     fn get_matching_user_files(
         workdir: &Path,
-        patterns: &[&str],
+        patterns: &[String],
     ) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
         let options = MatchOptions {
             case_sensitive: false,
@@ -1,15 +1,48 @@
+use super::{limits::Limits, policy::ForbidRule, sandbox::Sandbox};
 use std::path::{Path, PathBuf};
+use std::sync::{atomic::AtomicBool, Arc};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    /// Skip the remaining commands after the first failed one.
+    pub fail_fast: bool,
+    /// Only capture the output of commands without printing it.
+    pub quiet: bool,
+    /// The `CARGO_TARGET_DIR` shared by all the commands.
+    pub target_dir: Option<PathBuf>,
+    /// The wall-clock timeout of the commands which don't set their own.
+    pub timeout: Option<Duration>,
+    /// Names of the steps to launch, all of them if empty.
+    pub steps: Vec<String>,
+    /// Run the commands isolated from the course repository, the network and the environment.
+    pub sandbox: Option<Sandbox>,
+    /// Set to kill the running command and skip the remaining ones.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
 
 pub struct CommandContext {
     workdir: PathBuf,
     user_files: Vec<PathBuf>,
+    forbid: Vec<ForbidRule>,
+    limits: Limits,
+    options: LaunchOptions,
 }
 
 impl CommandContext {
-    pub fn new(workdir: &Path, user_files: &[PathBuf]) -> Self {
+    pub fn new(
+        workdir: &Path,
+        user_files: &[PathBuf],
+        forbid: &[ForbidRule],
+        limits: Limits,
+        options: &LaunchOptions,
+    ) -> Self {
         Self {
             workdir: workdir.to_path_buf(),
             user_files: user_files.to_vec(),
+            forbid: forbid.to_vec(),
+            limits,
+            options: options.clone(),
         }
     }
 
@@ -20,4 +53,16 @@ impl CommandContext {
     pub fn get_user_files(&self) -> &[PathBuf] {
         &self.user_files
     }
+
+    pub fn get_forbid_rules(&self) -> &[ForbidRule] {
+        &self.forbid
+    }
+
+    pub fn get_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn get_options(&self) -> &LaunchOptions {
+        &self.options
+    }
 }
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use std::{
+    io::{self, Read, Write},
+    mem,
+    process::{self, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long the output is still read after the command exits, as the processes it started
+/// in the background may keep the pipes open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+pub struct Execution {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    pub canceled: bool,
+}
+
+/// Puts the command into its own process group, so that the whole tree can be killed.
+fn isolate_process_group(cmd: &mut process::Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+}
+
+/// Kills the process group led by the child, the processes left of it after the child exits too.
+fn kill_group(child: &process::Child) {
+    #[cfg(unix)]
+    {
+        if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+            // SAFETY: kill has no memory safety requirements
+            unsafe { libc::kill(-pid, libc::SIGKILL) };
+        }
+    }
+}
+
+fn kill_tree(child: &mut process::Child) {
+    kill_group(child);
+    // The child may exit between the check and the kill, it's fine
+    let _ = child.kill();
+}
+
+/// Copies the output to `dst`, collecting it in `captured` so that it can be taken
+/// before the pipe is closed.
+fn tee<R: Read, W: Write>(mut src: R, mut dst: Option<W>, captured: &Mutex<Vec<u8>>) {
+    let mut buf = [0; 4096];
+    while let Ok(n) = src.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+        if let Some(dst) = dst.as_mut() {
+            let _ = dst.write_all(&buf[..n]);
+            let _ = dst.flush();
+        }
+        captured.lock().unwrap().extend_from_slice(&buf[..n]);
+    }
+}
+
+pub fn execute(
+    cmd: &mut process::Command,
+    timeout: Option<Duration>,
+    echo: bool,
+    cancel: Option<&AtomicBool>,
+) -> Result<Execution> {
+    // Without a timeout the command stays in our process group to receive Ctrl+C from the terminal
+    let isolated = timeout.is_some() || cancel.is_some();
+    if isolated {
+        isolate_process_group(cmd);
+    }
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn command")?;
+    let stdout = child.stdout.take().context("no stdout of the child")?;
+    let stderr = child.stderr.take().context("no stderr of the child")?;
+    let captured = Arc::new((Mutex::new(Vec::new()), Mutex::new(Vec::new())));
+    let readers = [
+        thread::spawn({
+            let captured = captured.clone();
+            move || tee(stdout, echo.then(io::stdout), &captured.0)
+        }),
+        thread::spawn({
+            let captured = captured.clone();
+            move || tee(stderr, echo.then(io::stderr), &captured.1)
+        }),
+    ];
+    let start = Instant::now();
+    let mut timed_out = false;
+    let mut canceled = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to wait for command")? {
+            break status;
+        }
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            timed_out = true;
+            kill_tree(&mut child);
+            break child.wait().context("failed to wait for killed command")?;
+        }
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            canceled = true;
+            kill_tree(&mut child);
+            break child.wait().context("failed to wait for killed command")?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    if isolated {
+        kill_group(&child);
+    }
+    // The readers left waiting on the pipes of the processes outside the group are abandoned
+    let grace_start = Instant::now();
+    while !readers.iter().all(|reader| reader.is_finished()) && grace_start.elapsed() < OUTPUT_GRACE
+    {
+        thread::sleep(POLL_INTERVAL / 5);
+    }
+    let stdout = mem::take(&mut *captured.0.lock().unwrap());
+    let stderr = mem::take(&mut *captured.1.lock().unwrap());
+    Ok(Execution {
+        status,
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        timed_out,
+        canceled,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_kills_process_tree() {
+        // The background sleep keeps the pipes open unless the whole group is killed
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", "echo started; sleep 30 & sleep 30"]);
+        let start = Instant::now();
+        let execution = execute(&mut cmd, Some(Duration::from_millis(300)), false, None).unwrap();
+        assert!(execution.timed_out);
+        assert!(!execution.status.success());
+        assert_eq!(execution.stdout, "started\n");
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let cancel = AtomicBool::new(true);
+        let mut cmd = process::Command::new("sleep");
+        cmd.arg("30");
+        let execution = execute(&mut cmd, None, false, Some(&cancel)).unwrap();
+        assert!(execution.canceled && !execution.timed_out);
+
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let execution = execute(&mut cmd, Some(Duration::from_secs(30)), false, None).unwrap();
+        assert_eq!(execution.status.code(), Some(3));
+        assert_eq!(
+            (execution.stdout.as_str(), execution.stderr.as_str()),
+            ("out\n", "err\n")
+        );
+    }
+
+    #[test]
+    fn background_processes_dont_block() {
+        for timeout in [Some(Duration::from_secs(30)), None] {
+            let mut cmd = process::Command::new("sh");
+            cmd.args(["-c", "sleep 30 & echo done"]);
+            let start = Instant::now();
+            let execution = execute(&mut cmd, timeout, false, None).unwrap();
+            assert!(execution.status.success() && !execution.timed_out);
+            assert_eq!(execution.stdout, "done\n");
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+    }
+}
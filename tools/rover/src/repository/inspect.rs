@@ -0,0 +1,663 @@
+use anyhow::{Context, Result};
+use proc_macro2::{Delimiter, Spacing, Span, TokenStream, TokenTree};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use syn::{
+    punctuated::Punctuated,
+    visit::{self, Visit},
+    Attribute, Expr, Meta, Token, UseTree,
+};
+
+/// Names brought into scope by the standard prelude which are interesting for the checks,
+/// along with whether the name refers to a macro.
+const PRELUDE: [(&str, &str, bool); 2] = [
+    ("Vec", "std::vec::Vec", false),
+    ("vec", "std::vec::vec", true),
+];
+
+pub struct Violation {
+    file: PathBuf,
+    line: usize,
+    message: String,
+}
+
+impl Violation {
+    pub fn new(file: &Path, line: usize, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+/// Maps the `core` and `alloc` re-exports onto `std`, e.g. `core::mem` onto `std::mem`.
+pub fn canonical_path(path: &str) -> String {
+    let path = path.trim_start_matches("::");
+    for krate in ["core", "alloc"] {
+        if path == krate || path.starts_with(&format!("{krate}::")) {
+            return format!("std{}", &path[krate.len()..]);
+        }
+    }
+    path.to_string()
+}
+
+/// A path used somewhere in the file, resolved through `use` declarations.
+pub struct PathUsage {
+    pub segments: Vec<String>,
+    pub line: usize,
+    /// The path came from the standard prelude rather than being written explicitly.
+    pub prelude: bool,
+    pub macro_call: bool,
+}
+
+impl PathUsage {
+    /// The resolved path with `core` and `alloc` re-exports mapped onto `std`.
+    pub fn canonical(&self) -> String {
+        canonical_path(&self.segments.join("::"))
+    }
+
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        let path = self.canonical();
+        path == prefix || path.starts_with(&format!("{prefix}::"))
+    }
+}
+
+pub struct SourceFile {
+    display_path: PathBuf,
+    ast: syn::File,
+}
+
+impl SourceFile {
+    pub fn parse(path: &Path, workdir: &Path) -> Result<Self> {
+        let display_path = path.strip_prefix(workdir).unwrap_or(path).to_path_buf();
+        let content =
+            fs::read_to_string(path).with_context(|| format!("failed to read file {path:?}"))?;
+        let ast = syn::parse_file(&content).map_err(|err| {
+            let start = err.span().start();
+            anyhow::anyhow!(
+                "{}:{}:{}: failed to parse: {err}",
+                display_path.display(),
+                start.line,
+                start.column + 1
+            )
+        })?;
+        Ok(Self { display_path, ast })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ast.attrs.is_empty() && self.ast.items.is_empty()
+    }
+
+    pub fn display_path(&self) -> &Path {
+        &self.display_path
+    }
+
+    pub fn violation(&self, line: usize, message: impl Into<String>) -> Violation {
+        Violation::new(&self.display_path, line, message)
+    }
+
+    /// Checks for an inner attribute like `#![no_std]` or `#![forbid(unsafe_code)]`.
+    pub fn has_inner_attribute(&self, name: &str, argument: Option<&str>) -> bool {
+        self.ast
+            .attrs
+            .iter()
+            .any(|attr| match (&attr.meta, argument) {
+                (meta, None) => meta.path().is_ident(name),
+                (Meta::List(list), Some(argument)) => {
+                    list.path.is_ident(name)
+                        && list.tokens.clone().into_iter().any(
+                            |token| matches!(token, TokenTree::Ident(ident) if ident == argument),
+                        )
+                }
+                _ => false,
+            })
+    }
+
+    pub fn unsafe_usages(&self) -> Vec<Violation> {
+        let mut visitor = UnsafeVisitor { found: Vec::new() };
+        visitor.visit_file(&self.ast);
+        visitor
+            .found
+            .into_iter()
+            .map(|(line, what)| self.violation(line, format!("{what} is not allowed")))
+            .collect()
+    }
+
+    pub fn path_usages(&self) -> Vec<PathUsage> {
+        let mut resolver = PathResolver::new(&self.ast);
+        resolver.visit_file(&self.ast);
+        resolver.usages
+    }
+
+    pub fn method_calls(&self) -> Vec<(String, usize)> {
+        let mut visitor = MethodCallVisitor { calls: Vec::new() };
+        visitor.visit_file(&self.ast);
+        visitor.calls
+    }
+
+    pub fn extern_crates(&self) -> Vec<(String, usize)> {
+        self.ast
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::ExternCrate(krate) => {
+                    Some((krate.ident.to_string(), line(krate.extern_token.span)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn line(span: Span) -> usize {
+    span.start().line
+}
+
+/// Parses macro arguments as comma-separated expressions, like in `println!` or `vec!`.
+fn macro_arguments(node: &syn::Macro) -> Option<Punctuated<Expr, Token![,]>> {
+    node.parse_body_with(Punctuated::parse_terminated).ok()
+}
+
+/// A path written in the tokens of a macro which are not expressions, e.g. in `macro_rules!`.
+struct TokenPath {
+    segments: Vec<String>,
+    leading_colon: bool,
+    line: usize,
+    macro_call: bool,
+}
+
+fn is_punct(tree: Option<&TokenTree>, c: char) -> bool {
+    matches!(tree, Some(TokenTree::Punct(punct)) if punct.as_char() == c)
+}
+
+fn is_path_separator(trees: &[TokenTree], i: usize) -> bool {
+    matches!(trees.get(i), Some(TokenTree::Punct(punct))
+        if punct.as_char() == ':' && punct.spacing() == Spacing::Joint)
+        && is_punct(trees.get(i + 1), ':')
+}
+
+/// Finds `a::b` paths and `.method(` calls in the raw tokens, for the macro bodies which
+/// can't be parsed. Metavariables like `$x` are skipped.
+fn scan_tokens(
+    tokens: TokenStream,
+    paths: &mut Vec<TokenPath>,
+    methods: &mut Vec<(String, usize)>,
+) {
+    let trees: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut i = 0;
+    while i < trees.len() {
+        let previous = i.checked_sub(1).and_then(|i| trees.get(i));
+        match &trees[i] {
+            TokenTree::Group(group) => scan_tokens(group.stream(), paths, methods),
+            TokenTree::Punct(punct)
+                if punct.as_char() == '.'
+                    && punct.spacing() == Spacing::Alone
+                    && !is_punct(previous, '.') =>
+            {
+                if let Some(TokenTree::Ident(method)) = trees.get(i + 1) {
+                    let is_call = is_path_separator(&trees, i + 2)
+                        || matches!(trees.get(i + 2), Some(TokenTree::Group(group))
+                            if group.delimiter() == Delimiter::Parenthesis);
+                    if is_call {
+                        methods.push((method.to_string(), line(method.span())));
+                    }
+                    i += 1;
+                }
+            }
+            TokenTree::Ident(ident) if !is_punct(previous, '$') => {
+                let leading_colon = i >= 2 && is_path_separator(&trees, i - 2);
+                let mut segments = vec![ident.to_string()];
+                while is_path_separator(&trees, i + 1) {
+                    match trees.get(i + 3) {
+                        Some(TokenTree::Ident(segment)) => {
+                            segments.push(segment.to_string());
+                            i += 3;
+                        }
+                        _ => break,
+                    }
+                }
+                let macro_call = is_punct(trees.get(i + 1), '!');
+                paths.push(TokenPath {
+                    segments,
+                    leading_colon,
+                    line: line(ident.span()),
+                    macro_call,
+                });
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+struct UnsafeVisitor {
+    found: Vec<(usize, &'static str)>,
+}
+
+impl UnsafeVisitor {
+    fn visit_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) if ident == "unsafe" => self
+                    .found
+                    .push((line(ident.span()), "unsafe code in macro")),
+                TokenTree::Group(group) => self.visit_tokens(group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeVisitor {
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.found
+            .push((line(node.unsafe_token.span), "unsafe block"));
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    fn visit_signature(&mut self, node: &'ast syn::Signature) {
+        if let Some(token) = &node.unsafety {
+            self.found.push((line(token.span), "unsafe fn"));
+        }
+        visit::visit_signature(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if let Some(token) = &node.unsafety {
+            self.found.push((line(token.span), "unsafe impl"));
+        }
+        visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        if let Some(token) = &node.unsafety {
+            self.found.push((line(token.span), "unsafe trait"));
+        }
+        visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast syn::ItemForeignMod) {
+        self.found
+            .push((line(node.abi.extern_token.span), "extern block"));
+        visit::visit_item_foreign_mod(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.visit_tokens(node.tokens.clone());
+        visit::visit_macro(self, node);
+    }
+}
+
+struct MethodCallVisitor {
+    calls: Vec<(String, usize)>,
+}
+
+impl<'ast> Visit<'ast> for MethodCallVisitor {
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        self.calls
+            .push((node.method.to_string(), line(node.method.span())));
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        match macro_arguments(node) {
+            Some(arguments) => {
+                for expr in &arguments {
+                    self.visit_expr(expr);
+                }
+            }
+            None => scan_tokens(node.tokens.clone(), &mut Vec::new(), &mut self.calls),
+        }
+    }
+}
+
+struct PathResolver {
+    /// Names imported by `use` declarations mapped onto their full paths.
+    aliases: HashMap<String, Vec<String>>,
+    /// Names defined in the file itself which shadow the prelude.
+    local: HashSet<String>,
+    usages: Vec<PathUsage>,
+}
+
+impl PathResolver {
+    fn new(file: &syn::File) -> Self {
+        let mut collector = ItemCollector {
+            aliases: HashMap::new(),
+            local: HashSet::new(),
+        };
+        collector.visit_file(file);
+        Self {
+            aliases: collector.aliases,
+            local: collector.local,
+            usages: Vec::new(),
+        }
+    }
+
+    fn resolve(&self, segments: Vec<String>, line: usize, macro_call: bool) -> PathUsage {
+        let first = segments.first().cloned().unwrap_or_default();
+        if let Some(alias) = self.aliases.get(&first) {
+            let segments = alias.iter().cloned().chain(segments.into_iter().skip(1));
+            return PathUsage {
+                segments: segments.collect(),
+                line,
+                prelude: false,
+                macro_call,
+            };
+        }
+        let prelude = PRELUDE.iter().find(|(name, _, is_macro)| {
+            *name == first && *is_macro == macro_call && !self.local.contains(&first)
+        });
+        match prelude {
+            Some((_, path, _)) => PathUsage {
+                segments: path
+                    .split("::")
+                    .map(String::from)
+                    .chain(segments.into_iter().skip(1))
+                    .collect(),
+                line,
+                prelude: true,
+                macro_call,
+            },
+            _ => PathUsage {
+                segments,
+                line,
+                prelude: false,
+                macro_call,
+            },
+        }
+    }
+}
+
+fn use_tree_paths(tree: &UseTree, prefix: &mut Vec<String>, out: &mut Vec<(Vec<String>, String)>) {
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            use_tree_paths(&path.tree, prefix, out);
+            prefix.pop();
+        }
+        UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            out.push((path, name.ident.to_string()));
+        }
+        UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            path.push(rename.ident.to_string());
+            out.push((path, rename.rename.to_string()));
+        }
+        UseTree::Glob(_) => {
+            let mut path = prefix.clone();
+            path.push("*".to_string());
+            out.push((path, "*".to_string()));
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                use_tree_paths(tree, prefix, out);
+            }
+        }
+    }
+}
+
+struct ItemCollector {
+    aliases: HashMap<String, Vec<String>>,
+    local: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for ItemCollector {
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        let mut paths = Vec::new();
+        use_tree_paths(&node.tree, &mut Vec::new(), &mut paths);
+        for (path, name) in paths {
+            let is_local = matches!(
+                path.first().map(String::as_str),
+                Some("crate" | "self" | "super")
+            );
+            if name == "*" || name == "_" {
+                continue;
+            }
+            if is_local {
+                self.local.insert(name);
+            } else if name == "self" {
+                let module = path[..path.len() - 1].to_vec();
+                if let Some(last) = module.last() {
+                    self.aliases.insert(last.clone(), module);
+                }
+            } else {
+                self.aliases.insert(name, path);
+            }
+        }
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        self.local.insert(node.ident.to_string());
+        visit::visit_item_struct(self, node);
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast syn::ItemEnum) {
+        self.local.insert(node.ident.to_string());
+        visit::visit_item_enum(self, node);
+    }
+
+    fn visit_item_type(&mut self, node: &'ast syn::ItemType) {
+        self.local.insert(node.ident.to_string());
+        visit::visit_item_type(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        self.local.insert(node.ident.to_string());
+        visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast syn::ItemMacro) {
+        if let Some(ident) = &node.ident {
+            self.local.insert(ident.to_string());
+        }
+        visit::visit_item_macro(self, node);
+    }
+
+    fn visit_generics(&mut self, node: &'ast syn::Generics) {
+        for param in node.type_params() {
+            self.local.insert(param.ident.to_string());
+        }
+        visit::visit_generics(self, node);
+    }
+}
+
+impl<'ast> Visit<'ast> for PathResolver {
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        let mut paths = Vec::new();
+        use_tree_paths(&node.tree, &mut Vec::new(), &mut paths);
+        let line = line(node.use_token.span);
+        for (mut path, _) in paths {
+            if path.last().map(String::as_str) == Some("self") {
+                path.pop();
+            }
+            let usage = if node.leading_colon.is_some() {
+                PathUsage {
+                    segments: path,
+                    line,
+                    prelude: false,
+                    macro_call: false,
+                }
+            } else {
+                let mut usage = self.resolve(path, line, false);
+                usage.prelude = false;
+                usage
+            };
+            self.usages.push(usage);
+        }
+    }
+
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        let segments: Vec<String> = node
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let line = node
+            .segments
+            .first()
+            .map(|segment| line(segment.ident.span()))
+            .unwrap_or_default();
+        let usage = if node.leading_colon.is_some() {
+            PathUsage {
+                segments,
+                line,
+                prelude: false,
+                macro_call: false,
+            }
+        } else {
+            self.resolve(segments, line, false)
+        };
+        self.usages.push(usage);
+        visit::visit_path(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let segments: Vec<String> = node
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let line = line(node.bang_token.span);
+        let usage = if node.path.leading_colon.is_some() {
+            PathUsage {
+                segments,
+                line,
+                prelude: false,
+                macro_call: true,
+            }
+        } else {
+            self.resolve(segments, line, true)
+        };
+        self.usages.push(usage);
+        match macro_arguments(node) {
+            Some(arguments) => {
+                for expr in &arguments {
+                    self.visit_expr(expr);
+                }
+            }
+            None => {
+                let mut paths = Vec::new();
+                scan_tokens(node.tokens.clone(), &mut paths, &mut Vec::new());
+                for path in paths {
+                    let usage = if path.leading_colon {
+                        PathUsage {
+                            segments: path.segments,
+                            line: path.line,
+                            prelude: false,
+                            macro_call: path.macro_call,
+                        }
+                    } else {
+                        self.resolve(path.segments, path.line, path.macro_call)
+                    };
+                    // Lone identifiers are mostly local names, only the imported ones matter
+                    if usage.segments.len() > 1 || usage.macro_call {
+                        self.usages.push(usage);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_attribute(&mut self, _: &'ast Attribute) {
+        // Attributes like `#[derive(...)]` are not interesting for the checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(content: &str) -> SourceFile {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(&path, content).unwrap();
+        SourceFile::parse(&path, dir.path()).unwrap()
+    }
+
+    fn usages(source: &SourceFile) -> Vec<(String, usize)> {
+        source
+            .path_usages()
+            .into_iter()
+            .map(|usage| (usage.canonical(), usage.line))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_paths_and_aliases() {
+        let source = source(
+            "use core::cell::RefCell as Cell;\n\
+             fn f() -> Vec<u8> { let c = Cell::new(1); ::std::process::exit(0) }\n",
+        );
+        let usages = usages(&source);
+        for expected in [
+            ("std::cell::RefCell", 1),
+            ("std::cell::RefCell::new", 2),
+            ("std::vec::Vec", 2),
+            ("std::process::exit", 2),
+        ] {
+            assert!(
+                usages.contains(&(expected.0.to_string(), expected.1)),
+                "{expected:?} not in {usages:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_method_calls() {
+        let source =
+            source("fn f(v: &[u8]) -> usize { println!(\"{}\", v.len()); v.iter().count() }");
+        assert_eq!(
+            source.method_calls(),
+            [
+                ("len".to_string(), 1),
+                ("count".to_string(), 1),
+                ("iter".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_unparsed_macro_bodies() {
+        let source = source(
+            "use std::collections::HashSet as Set;\n\
+             macro_rules! mk {\n\
+                 () => { std::collections::HashMap::<i32, i32>::new() };\n\
+                 ($v:ident) => { Set::<i32>::new().$v.drain().into_iter() };\n\
+                 ($x:expr) => { vec![$x].len() };\n\
+             }\n",
+        );
+        let usages = usages(&source);
+        for expected in [
+            ("std::collections::HashMap", 3),
+            ("std::collections::HashSet", 4),
+            ("std::vec::vec", 5),
+        ] {
+            assert!(
+                usages.contains(&(expected.0.to_string(), expected.1)),
+                "{expected:?} not in {usages:?}"
+            );
+        }
+        assert!(!usages.iter().any(|(path, _)| path == "v" || path == "x"));
+        assert_eq!(
+            source.method_calls(),
+            [
+                ("drain".to_string(), 4),
+                ("into_iter".to_string(), 4),
+                ("len".to_string(), 5)
+            ]
+        );
+    }
+}
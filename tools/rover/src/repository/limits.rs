@@ -0,0 +1,88 @@
+use crate::util::{duration::deserialize_duration, size::deserialize_size};
+use serde::Deserialize;
+use std::{process, time::Duration};
+
+/// Resource limits applied to every process launched by the commands.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Limits {
+    /// Maximum size of the address space of each process, in bytes.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    memory: Option<u64>,
+    /// Maximum CPU time of each process.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    cpu_time: Option<Duration>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpu_time.is_none()
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut process::Command) {
+        use std::os::unix::process::CommandExt;
+
+        if self.is_empty() {
+            return;
+        }
+        let memory = self.memory;
+        let cpu_time = self.cpu_time.map(|cpu_time| cpu_time.as_secs().max(1));
+        let set_limit = |resource, value: u64| {
+            let limit = libc::rlimit {
+                rlim_cur: value as libc::rlim_t,
+                rlim_max: value as libc::rlim_t,
+            };
+            // SAFETY: setrlimit only reads the passed struct
+            if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        };
+        // SAFETY: the closure only calls async-signal-safe setrlimit and doesn't allocate
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(memory) = memory {
+                    set_limit(libc::RLIMIT_AS, memory)?;
+                }
+                if let Some(cpu_time) = cpu_time {
+                    set_limit(libc::RLIMIT_CPU, cpu_time)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _cmd: &mut process::Command) {
+        if !self.is_empty() {
+            eprintln!("warning: resource limits are only supported on Linux");
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    #[test]
+    fn limits() {
+        let limits: Limits = serde_yaml::from_str("{memory: 64M, cpu-time: 1s}").unwrap();
+        assert_eq!(limits.memory, Some(64 << 20));
+        assert!(serde_yaml::from_str::<Limits>("{memory: 64X}").is_err());
+        assert!(serde_yaml::from_str::<Limits>("{stack: 1M}").is_err());
+
+        let limits: Limits = serde_yaml::from_str("cpu-time: 1s").unwrap();
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", "while :; do :; done"]);
+        limits.apply(&mut cmd);
+        let status = cmd.status().unwrap();
+        // Depending on the kernel the soft limit sends SIGXCPU or the hard one SIGKILL
+        assert!(
+            matches!(status.signal(), Some(libc::SIGXCPU | libc::SIGKILL)),
+            "{status}"
+        );
+    }
+}
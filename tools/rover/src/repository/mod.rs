@@ -1,8 +1,16 @@
 mod command;
 mod config;
-mod context;
+pub mod context;
 mod copying;
+mod execution;
+mod inspect;
+mod limits;
+mod policy;
 pub mod problem;
+pub mod protected;
 pub mod repo;
+pub mod sandbox;
+mod scoring;
+pub mod settings;
 mod step;
 mod toolchain;
@@ -0,0 +1,275 @@
+use super::inspect::{canonical_path, SourceFile, Violation};
+use anyhow::{bail, Context, Result};
+use glob::Pattern;
+use serde::{Deserialize, Deserializer};
+use std::{fs, path::Path};
+
+const CARGO_TOML: &str = "Cargo.toml";
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A single entry of the `forbid` section of the problem config.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ForbidRule {
+    #[serde(default, deserialize_with = "deserialize_path")]
+    path: Option<String>,
+    #[serde(rename = "macro", default, deserialize_with = "deserialize_path")]
+    macro_name: Option<String>,
+    method: Option<String>,
+    #[serde(rename = "crate")]
+    crate_name: Option<String>,
+    #[serde(default)]
+    files: Vec<String>,
+    message: Option<String>,
+}
+
+/// The paths are matched against the canonical paths of the usages, so they are
+/// canonicalized the same way.
+fn deserialize_path<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.map(|path| canonical_path(&path)))
+}
+
+impl ForbidRule {
+    pub fn validate(&self) -> Result<()> {
+        let targets = [&self.path, &self.macro_name, &self.method, &self.crate_name];
+        if targets.iter().filter(|target| target.is_some()).count() != 1 {
+            bail!(
+                "forbid rule must have exactly one of \"path\", \"macro\", \"method\" or \"crate\""
+            )
+        }
+        for pattern in &self.files {
+            Pattern::new(pattern).with_context(|| format!("invalid files pattern {pattern:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, file: &Path) -> bool {
+        self.files.is_empty()
+            || self.files.iter().any(|pattern| {
+                Pattern::new(pattern)
+                    .map(|pattern| pattern.matches_path(file))
+                    .unwrap_or(false)
+            })
+    }
+
+    fn describe(&self, what: String) -> String {
+        match &self.message {
+            Some(message) => format!("{what} is forbidden: {message}"),
+            None => format!("{what} is forbidden"),
+        }
+    }
+
+    fn check_source(&self, source: &SourceFile) -> Vec<Violation> {
+        if !self.applies_to(source.display_path()) {
+            return Vec::new();
+        }
+        let mut violations = Vec::new();
+        if let Some(path) = &self.path {
+            for usage in source.path_usages() {
+                if usage.starts_with(path) {
+                    let what = format!("use of '{}'", usage.canonical());
+                    violations.push(source.violation(usage.line, self.describe(what)));
+                } else if usage.segments.last().map(String::as_str) == Some("*") {
+                    let module =
+                        canonical_path(&usage.segments[..usage.segments.len() - 1].join("::"));
+                    if path.starts_with(&format!("{module}::")) {
+                        let what = format!("glob import from '{module}' which contains '{path}'");
+                        violations.push(source.violation(usage.line, self.describe(what)));
+                    }
+                }
+            }
+        }
+        if let Some(name) = &self.macro_name {
+            let name = name.trim_end_matches('!');
+            for usage in source.path_usages() {
+                let matches = if name.contains("::") {
+                    usage.starts_with(name)
+                } else {
+                    usage.segments.last().map(String::as_str) == Some(name)
+                };
+                if usage.macro_call && matches {
+                    let what = format!("macro '{name}!'");
+                    violations.push(source.violation(usage.line, self.describe(what)));
+                }
+            }
+        }
+        if let Some(method) = &self.method {
+            for (name, line) in source.method_calls() {
+                if name == *method {
+                    let what = format!("method '{name}'");
+                    violations.push(source.violation(line, self.describe(what)));
+                }
+            }
+        }
+        if let Some(krate) = &self.crate_name {
+            let krate = krate.replace('-', "_");
+            for (name, line) in source.extern_crates() {
+                if name == krate {
+                    let what = format!("crate '{name}'");
+                    violations.push(source.violation(line, self.describe(what)));
+                }
+            }
+            for usage in source.path_usages() {
+                if !usage.macro_call && usage.segments.len() > 1 && usage.segments[0] == krate {
+                    let what = format!("use of '{}' from crate '{krate}'", usage.canonical());
+                    violations.push(source.violation(usage.line, self.describe(what)));
+                }
+            }
+        }
+        violations
+    }
+
+    fn check_manifest(&self, manifest: &toml::Value, content: &str) -> Vec<Violation> {
+        let krate = match &self.crate_name {
+            Some(krate) => krate.replace('-', "_"),
+            None => return Vec::new(),
+        };
+        let mut tables: Vec<&toml::Value> = DEPENDENCY_TABLES
+            .iter()
+            .filter_map(|table| manifest.get(table))
+            .collect();
+        if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+            for target in targets.values() {
+                tables.extend(
+                    DEPENDENCY_TABLES
+                        .iter()
+                        .filter_map(|table| target.get(table)),
+                );
+            }
+        }
+        let mut violations = Vec::new();
+        for (name, dependency) in tables
+            .into_iter()
+            .filter_map(toml::Value::as_table)
+            .flat_map(|table| table.iter())
+        {
+            let package = dependency
+                .get("package")
+                .and_then(toml::Value::as_str)
+                .unwrap_or(name);
+            if package.replace('-', "_") == krate {
+                let line = content
+                    .lines()
+                    .position(|line| {
+                        let line = line.trim_start();
+                        line.starts_with(name.as_str())
+                            && line[name.len()..].trim_start().starts_with(['=', '.'])
+                            || line.ends_with(&format!(".{name}]"))
+                    })
+                    .map_or(1, |line| line + 1);
+                let what = format!("dependency '{name}'");
+                violations.push(Violation::new(
+                    Path::new(CARGO_TOML),
+                    line,
+                    self.describe(what),
+                ));
+            }
+        }
+        violations
+    }
+}
+
+pub fn check_source(rules: &[ForbidRule], source: &SourceFile) -> Vec<Violation> {
+    rules
+        .iter()
+        .flat_map(|rule| rule.check_source(source))
+        .collect()
+}
+
+/// Checks the crate rules against the dependencies in the problem's `Cargo.toml`.
+pub fn check_manifest(rules: &[ForbidRule], workdir: &Path) -> Result<Vec<Violation>> {
+    let manifest_path = workdir.join(CARGO_TOML);
+    if !manifest_path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {manifest_path:?}"))?;
+    let manifest: toml::Value =
+        toml::from_str(&content).with_context(|| format!("failed to parse {manifest_path:?}"))?;
+    Ok(rules
+        .iter()
+        .flat_map(|rule| rule.check_manifest(&manifest, &content))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(yml: &str) -> ForbidRule {
+        let rule: ForbidRule = serde_yaml::from_str(yml).unwrap();
+        rule.validate().unwrap();
+        rule
+    }
+
+    fn violations(rule: &ForbidRule, content: &str) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(&path, content).unwrap();
+        let source = SourceFile::parse(&path, dir.path()).unwrap();
+        rule.check_source(&source)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn paths_are_canonical() {
+        let transmute = rule("path: core::mem::transmute");
+        assert_eq!(
+            violations(
+                &transmute,
+                "use std::mem;\nfn f() { mem::transmute::<u8, i8>(1); }"
+            ),
+            ["lib.rs:2: use of 'std::mem::transmute' is forbidden"]
+        );
+        assert_eq!(
+            violations(&transmute, "fn f() { alloc::vec::Vec::<u8>::new(); }"),
+            Vec::<String>::new()
+        );
+        let alloc = rule("{path: alloc::collections, message: use your own}");
+        assert_eq!(
+            violations(
+                &alloc,
+                "use core::cell::Cell;\nuse std::collections::VecDeque;"
+            ),
+            ["lib.rs:2: use of 'std::collections::VecDeque' is forbidden: use your own"]
+        );
+    }
+
+    #[test]
+    fn paths_match_by_segments() {
+        let hash = rule("path: std::collections::Hash");
+        assert!(violations(&hash, "use std::collections::HashMap;").is_empty());
+        assert_eq!(violations(&hash, "use std::collections::Hash;").len(), 1);
+        let map = rule("path: std::collections::HashMap");
+        assert_eq!(
+            violations(&map, "use core::collections::*;"),
+            [
+                "lib.rs:1: glob import from 'std::collections' which contains \
+              'std::collections::HashMap' is forbidden"
+            ]
+        );
+    }
+
+    #[test]
+    fn macros_methods_and_files() {
+        let vec = rule("macro: vec!");
+        assert_eq!(
+            violations(&vec, "fn f() { let v = vec![1]; }"),
+            ["lib.rs:1: macro 'vec!' is forbidden"]
+        );
+        let sort = rule("{method: sort, files: [\"src/*.rs\"]}");
+        assert!(violations(&sort, "fn f(v: &mut [u8]) { v.sort() }").is_empty());
+        let sort = rule("method: sort");
+        assert_eq!(
+            violations(&sort, "fn f(v: &mut [u8]) { v.sort(); v.sort_unstable() }"),
+            ["lib.rs:1: method 'sort' is forbidden"]
+        );
+
+        let both: ForbidRule = serde_yaml::from_str("{path: std::mem, method: swap}").unwrap();
+        assert!(both.validate().is_err());
+    }
+}
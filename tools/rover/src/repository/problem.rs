@@ -1,22 +1,43 @@
-use super::config::Config;
-use super::repo::PROBLEMS_FOLDER;
-use crate::{launch_git, repository::copying::copy_files};
+use super::{
+    config::Config,
+    context::LaunchOptions,
+    protected::{Manifest, PROTECTED_FILE},
+};
+use crate::{
+    git::git_repo::GitRepo,
+    repository::copying::copy_files,
+    testing::result::{CommandResult, StepResult, TestingResult},
+    util::hash::hash_files,
+};
 use anyhow::{bail, Result};
 use std::{
     path::{Path, PathBuf},
-    process,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
 };
 
-const DEFAULT_YML_NAME: &str = ".config.yml";
+pub const DEFAULT_YML_NAME: &str = ".config.yml";
 
 pub struct Problem {
     path: PathBuf,
+    relative_path: PathBuf,
+    branch_name: String,
+    /// Whether the problem comes from compose, so it must have the protected files manifest.
+    composed: bool,
 }
 
 impl Problem {
-    pub(super) fn from_path(path: &Path) -> Self {
+    pub(super) fn new(
+        path: &Path,
+        relative_path: PathBuf,
+        branch_name: String,
+        composed: bool,
+    ) -> Self {
         Self {
             path: path.to_path_buf(),
+            relative_path,
+            branch_name,
+            composed,
         }
     }
 
@@ -35,30 +56,143 @@ impl Problem {
             .to_string()
     }
 
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn branch_name(&self) -> String {
-        self.group() + "/" + &self.title()
+        self.branch_name.clone()
     }
 
     pub fn relative_path(&self) -> PathBuf {
-        PathBuf::from(PROBLEMS_FOLDER)
-            .join(self.group())
-            .join(self.title())
+        self.relative_path.clone()
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.path.join(DEFAULT_YML_NAME)
+    }
+
+    /// Hash of the testing config and the user files, which identifies a testing run.
+    pub fn fingerprint(&self) -> Result<String> {
+        let config = self.config()?;
+        let mut files = config.get_relative_user_files().to_vec();
+        files.push(PathBuf::from(DEFAULT_YML_NAME));
+        hash_files(&self.path, &files)
     }
 
     pub fn config(&self) -> Result<Config> {
-        Config::from_yml(&self.path.join(DEFAULT_YML_NAME))
+        Config::from_yml(&self.config_path())
+    }
+
+    /// Fails if the files outside of `allowed-patterns` differ from the manifest made by compose,
+    /// or if files changing the build or the tests were added.
+    pub fn check_protected_files(&self) -> Result<()> {
+        let path = self.path.join(PROTECTED_FILE);
+        if !path.is_file() {
+            if self.composed {
+                bail!(
+                    "{PROTECTED_FILE} of {} is missing, restore it to test the solution",
+                    self.branch_name()
+                )
+            }
+            return Ok(());
+        }
+        let manifest = Manifest::from_yml(&path)?;
+        let list = |paths: &[&Path]| -> String {
+            paths
+                .iter()
+                .map(|path| format!("  {}", path.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let modified = manifest.modified_files(&self.path);
+        if !modified.is_empty() {
+            bail!(
+                "protected files of {} were modified, restore them to test the solution:\n{}",
+                self.branch_name(),
+                list(&modified)
+            )
+        }
+        let config = self.config()?;
+        let unlisted = manifest.unlisted_files(&self.path, config.get_relative_user_files())?;
+        if !unlisted.is_empty() {
+            let unlisted: Vec<_> = unlisted.iter().map(PathBuf::as_path).collect();
+            bail!(
+                "files added to {} change its build or tests, remove them to test the solution:\n{}",
+                self.branch_name(),
+                list(&unlisted)
+            )
+        }
+        Ok(())
     }
 
-    pub fn launch_all_steps(&self) -> Result<()> {
+    pub fn launch_all_steps(&self, options: &LaunchOptions) -> Result<TestingResult> {
+        self.check_protected_files()?;
         let config = self.config()?;
         let toolchain = config.get_toolchain();
-        let context = config.get_command_context();
+        let context = config.get_command_context(options);
+        for name in &options.steps {
+            if !config.get_steps().iter().any(|step| step.name() == name) {
+                bail!("problem {} has no step \"{name}\"", self.branch_name())
+            }
+        }
+        if let Some(sandbox) = &options.sandbox {
+            sandbox.prepare(&self.path)?;
+        }
+        let mut failed = false;
+        let mut steps = Vec::new();
         for step in config.get_steps() {
-            for command in step.commands() {
-                toolchain.run_command(command, &context)?;
+            if !options.steps.is_empty() && !options.steps.iter().any(|name| name == step.name()) {
+                continue;
+            }
+            let step_start = Instant::now();
+            let mut commands = Vec::new();
+            for entry in step.commands() {
+                let command = &entry.command;
+                let step_left = step
+                    .timeout()
+                    .map(|timeout| timeout.saturating_sub(step_start.elapsed()));
+                let canceled = options
+                    .cancel
+                    .as_ref()
+                    .is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+                if (failed && options.fail_fast) || step_left == Some(Duration::ZERO) || canceled {
+                    commands.push(CommandResult::skipped(command.name().to_string()));
+                    continue;
+                }
+                let timeout = match (entry.timeout.or(options.timeout), step_left) {
+                    (Some(timeout), Some(left)) => Some(timeout.min(left)),
+                    (timeout, left) => timeout.or(left),
+                };
+                let start = Instant::now();
+                let result = toolchain
+                    .run_command(command, timeout, &context)
+                    .unwrap_or_else(|err| {
+                        CommandResult::failed_to_launch(
+                            command.name().to_string(),
+                            start.elapsed(),
+                            &err,
+                        )
+                    });
+                failed |= result.outcome.is_failure();
+                commands.push(result);
             }
+            steps.push(StepResult::new(step.name().to_string(), commands));
         }
-        Ok(())
+        let mut result = TestingResult::new(self.branch_name(), steps);
+        config.get_scoring().apply(&mut result);
+        Ok(result)
+    }
+
+    /// Paths of the files the student submits, relative to the repository root.
+    pub fn solution_files(&self) -> Result<Vec<PathBuf>> {
+        let relative_path = self.relative_path();
+        Ok(self
+            .config()?
+            .get_relative_user_files()
+            .iter()
+            .map(|file| relative_path.join(file))
+            .collect())
     }
 
     pub fn move_solution_files_from(
@@ -71,8 +205,8 @@ impl Problem {
         let branch_name = self.branch_name();
         let solutions_problem_path = solutions_repo.join(relative_path);
         let repository_problem_path = self.path.clone();
-        if checkout_branch && !launch_git!(solutions_repo, "checkout", &branch_name) {
-            bail!("failed to checkout branch in solutions repository")
+        if checkout_branch {
+            GitRepo::open(solutions_repo)?.checkout_branch(&branch_name)?;
         }
         copy_files(
             &solutions_problem_path,
@@ -88,23 +222,11 @@ impl Problem {
         let branch_name = self.branch_name();
         let solutions_problem_path = solutions_repo.join(relative_path);
         let repository_problem_path = self.path.clone();
-        if !launch_git!(solutions_repo, "checkout", &branch_name)
-            && !launch_git!(
-                solutions_repo,
-                "checkout",
-                "-b",
-                &branch_name,
-                "origin/master"
-            )
-            && !launch_git!(
-                solutions_repo,
-                "checkout",
-                "-b",
-                &branch_name,
-                "origin/main"
-            )
-        {
-            bail!("failed to both checkout and create branch in solutions repository")
+        let git = GitRepo::open(solutions_repo)?;
+        if git.has_branch(&branch_name) {
+            git.checkout_branch(&branch_name)?;
+        } else {
+            git.checkout_new_branch(&branch_name, &git.default_branch()?)?;
         }
         copy_files(
             &repository_problem_path,
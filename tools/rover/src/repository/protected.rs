@@ -0,0 +1,137 @@
+use crate::util::hash::to_hex;
+use anyhow::{Context, Result};
+use glob::{glob, Pattern};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const PROTECTED_FILE: &str = ".protected.yml";
+
+/// The files which change the build or the tests of a problem when added.
+const BUILD_PATTERNS: [&str; 3] = ["build.rs", ".cargo/**/*", "tests/**/*"];
+
+const HEADER: &str = "# Hashes of the files students must not modify, generated by rover compose\n";
+
+/// Hashes of the files of a problem outside of its `allowed-patterns`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    files: BTreeMap<PathBuf, String>,
+}
+
+fn hash(content: &[u8]) -> String {
+    to_hex(&Sha256::digest(content))
+}
+
+impl Manifest {
+    /// Adds the file by its path relative to the problem.
+    pub fn insert(&mut self, path: PathBuf, content: &[u8]) {
+        self.files.insert(path, hash(content));
+    }
+
+    pub fn to_yml(&self) -> Result<String> {
+        Ok(HEADER.to_string() + &serde_yaml::to_string(self)?)
+    }
+
+    pub fn from_yml(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        serde_yaml::from_str(&text).with_context(|| format!("invalid manifest {path:?}"))
+    }
+
+    /// The files of the problem in `workdir` which are missing or differ from the manifest.
+    pub fn modified_files(&self, workdir: &Path) -> Vec<&Path> {
+        self.files
+            .iter()
+            .filter(|(path, expected)| {
+                fs::read(workdir.join(path)).map_or(true, |content| hash(&content) != **expected)
+            })
+            .map(|(path, _)| path.as_path())
+            .collect()
+    }
+
+    /// The files of the problem in `workdir` which change its build or tests, but are neither
+    /// in the manifest nor `allowed` to students.
+    pub fn unlisted_files(&self, workdir: &Path, allowed: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let root = Pattern::escape(workdir.to_str().context("problem path is not UTF-8")?);
+        let mut unlisted = Vec::new();
+        for pattern in BUILD_PATTERNS {
+            for path in glob(&format!("{root}/{pattern}"))? {
+                let path = path?;
+                let relative = path.strip_prefix(workdir)?.to_path_buf();
+                if path.is_file()
+                    && !self.files.contains_key(&relative)
+                    && !allowed.contains(&relative)
+                {
+                    unlisted.push(relative);
+                }
+            }
+        }
+        unlisted.sort();
+        Ok(unlisted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("tests")).unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        fs::write(dir.path().join("tests/tests.rs"), "#[test]").unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest.insert(PathBuf::from("Cargo.toml"), b"[package]");
+        manifest.insert(PathBuf::from("tests/tests.rs"), b"#[test]");
+        manifest.insert(PathBuf::from("build.rs"), b"fn main() {}");
+        let path = dir.path().join(PROTECTED_FILE);
+        fs::write(&path, manifest.to_yml().unwrap()).unwrap();
+        let manifest = Manifest::from_yml(&path).unwrap();
+        assert_eq!(manifest.modified_files(dir.path()), [Path::new("build.rs")]);
+
+        fs::write(dir.path().join("tests/tests.rs"), "").unwrap();
+        assert_eq!(
+            manifest.modified_files(dir.path()),
+            [Path::new("build.rs"), Path::new("tests/tests.rs")]
+        );
+    }
+
+    #[test]
+    fn detects_unlisted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [
+            "Cargo.toml",
+            "build.rs",
+            "notes.md",
+            "src/lib.rs",
+            "src/helper.rs",
+            ".cargo/config.toml",
+            "tests/tests.rs",
+            "tests/extra/mod.rs",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let mut manifest = Manifest::default();
+        manifest.insert(PathBuf::from("Cargo.toml"), b"");
+        manifest.insert(PathBuf::from("tests/tests.rs"), b"");
+        let allowed = [
+            PathBuf::from("src/lib.rs"),
+            PathBuf::from("tests/extra/mod.rs"),
+        ];
+        assert_eq!(
+            manifest.unlisted_files(dir.path(), &allowed).unwrap(),
+            [
+                PathBuf::from(".cargo/config.toml"),
+                PathBuf::from("build.rs"),
+            ]
+        );
+    }
+}
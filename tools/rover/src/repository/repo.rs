@@ -1,32 +1,90 @@
-use super::problem::Problem;
+use super::{
+    problem::{Problem, DEFAULT_YML_NAME},
+    sandbox::Sandbox,
+    settings::Settings,
+};
 use crate::compose;
 use anyhow::{bail, Context, Result};
-use std::path::{Path, PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+pub const SETTINGS_FILE: &str = ".rover.toml";
 pub const COMPOSE_CONFIG: &str = ".compose.yml";
-pub const PROBLEMS_FOLDER: &str = "problems";
-pub const SOLUTIONS_REPO_FOLDER: &str = "solutions";
-pub const REPOSITORY_NAMES: [&str; 2] = ["rust2023", "rust2023-private"];
+pub const DEADLINES_FILE: &str = ".deadlines.yml";
+pub const TARGET_FOLDER: &str = "target";
+const WORKSPACE_MANIFEST: &str = "Cargo.toml";
 
 pub struct Repository {
     path: PathBuf,
+    settings: Settings,
+}
+
+fn is_workspace_root(dir: &Path) -> bool {
+    fs::read_to_string(dir.join(WORKSPACE_MANIFEST))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|manifest| manifest.contains_key("workspace"))
 }
 
 impl Repository {
+    /// Finds the course repository containing the path: the closest directory with
+    /// a `.rover.toml`, otherwise with a `.compose.yml`, otherwise the cargo workspace root.
     pub fn from_path(path: &Path) -> Result<Self> {
         let path = path
             .canonicalize()
             .context("cannot canonicalize path for repository")?;
-        let prefix_count = path
-            .iter()
-            .position(|comp| REPOSITORY_NAMES.iter().any(|name| *name == comp));
-        if let Some(pos) = prefix_count {
-            Ok(Self {
-                path: path.iter().take(pos + 1).collect(),
+        let root = path
+            .ancestors()
+            .find(|dir| dir.join(SETTINGS_FILE).is_file())
+            .or_else(|| {
+                path.ancestors()
+                    .find(|dir| dir.join(COMPOSE_CONFIG).is_file())
             })
+            .or_else(|| path.ancestors().find(|dir| is_workspace_root(dir)))
+            .with_context(|| {
+                format!(
+                    "{path:?} is not within a course repository, add {SETTINGS_FILE} to its root"
+                )
+            })?;
+        let settings_path = root.join(SETTINGS_FILE);
+        let settings = if settings_path.is_file() {
+            Settings::from_toml(&settings_path)?
         } else {
-            bail!("path does not contain course repository")
-        }
+            Settings::default()
+        };
+        Ok(Self {
+            path: root.to_path_buf(),
+            settings,
+        })
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The problems folder relative to the repository.
+    pub fn problems_folder(&self) -> &Path {
+        self.settings.get_problems_folder()
+    }
+
+    /// The problem in `REPOSITORY/PROBLEMS_FOLDER/GROUP/TITLE`.
+    fn problem(&self, path: &Path) -> Problem {
+        let relative_path: PathBuf = path.iter().skip(self.path.iter().count()).collect();
+        let mut names = relative_path
+            .iter()
+            .rev()
+            .map(|name| name.to_string_lossy());
+        let title = names.next().unwrap_or_default();
+        let group = names.next().unwrap_or_default();
+        let branch_name = self.settings.branch_name(&group, &title);
+        Problem::new(
+            path,
+            relative_path,
+            branch_name,
+            self.settings.is_composed(),
+        )
     }
 
     pub fn problem_from_path(&self, path: &Path) -> Result<Problem> {
@@ -47,19 +105,61 @@ impl Repository {
             .to_str()
             .unwrap()
             .to_string();
-        if self.path.join(PROBLEMS_FOLDER).join(group).join(title) == path {
-            Ok(Problem::from_path(&path))
+        if self
+            .path
+            .join(self.problems_folder())
+            .join(group)
+            .join(title)
+            == path
+        {
+            Ok(self.problem(&path))
         } else {
-            bail!("problem path is not in REPOSITORY/problems/GROUP/TITLE")
+            bail!(
+                "problem path is not in REPOSITORY/{}/GROUP/TITLE",
+                self.problems_folder().display()
+            )
+        }
+    }
+
+    /// All the problems in `REPOSITORY/problems/GROUP/TITLE` which have a testing config.
+    pub fn problems(&self) -> Result<Vec<Problem>> {
+        let problems_dir = self.path.join(self.problems_folder());
+        let mut problems = Vec::new();
+        for group in fs::read_dir(&problems_dir)
+            .with_context(|| format!("failed to read directory {problems_dir:?}"))?
+        {
+            let group = group.context("failed to read problem group")?.path();
+            if !group.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&group)
+                .with_context(|| format!("failed to read directory {group:?}"))?
+            {
+                let path = entry.context("failed to read problem entry")?.path();
+                if path.join(DEFAULT_YML_NAME).is_file() {
+                    problems.push(self.problem(&path));
+                }
+            }
         }
+        problems.sort_by_key(Problem::branch_name);
+        Ok(problems)
+    }
+
+    pub fn target_dir(&self) -> PathBuf {
+        self.path.join(TARGET_FOLDER)
+    }
+
+    /// The sandbox keeping the repository read-only with a private target directory.
+    pub fn sandbox(&self) -> Sandbox {
+        Sandbox::new(&self.path, self.target_dir().join("sandbox"))
     }
 
     pub fn solutions_repo(&self) -> Result<PathBuf> {
-        let path = self.path.parent().unwrap().join(SOLUTIONS_REPO_FOLDER);
+        let path = self.path.join(self.settings.get_solutions_repo());
         if path.is_dir() {
             Ok(path)
         } else {
-            bail!("solutions repository does not exist")
+            bail!("solutions repository {path:?} does not exist")
         }
     }
 
@@ -67,8 +167,75 @@ impl Repository {
         compose::config::Config::from_yml(&self.path.join(COMPOSE_CONFIG))
     }
 
-    #[allow(unused)]
     pub fn get_path(&self) -> &Path {
         &self.path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A temporary directory with `course/problems/intro/add` in it, returned canonical.
+    fn course() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("course/problems/intro/add")).unwrap();
+        (dir, root)
+    }
+
+    fn root_of(path: &Path) -> PathBuf {
+        Repository::from_path(path)
+            .unwrap()
+            .get_path()
+            .to_path_buf()
+    }
+
+    #[test]
+    fn finds_root_by_each_marker() {
+        for (marker, content) in [
+            (SETTINGS_FILE, ""),
+            (COMPOSE_CONFIG, ""),
+            (WORKSPACE_MANIFEST, "[workspace]\n"),
+        ] {
+            let (_dir, root) = course();
+            fs::write(root.join("course").join(marker), content).unwrap();
+            let problem = root.join("course/problems/intro/add");
+            assert_eq!(root_of(&problem), root.join("course"), "{marker}");
+        }
+    }
+
+    #[test]
+    fn markers_win_in_order() {
+        let (_dir, root) = course();
+        let problem = root.join("course/problems/intro/add");
+        // A manifest without a workspace is a problem, not the course root
+        fs::write(problem.join(WORKSPACE_MANIFEST), "[package]\n").unwrap();
+        fs::write(
+            root.join("course/problems").join(WORKSPACE_MANIFEST),
+            "[workspace]\n",
+        )
+        .unwrap();
+        assert_eq!(root_of(&problem), root.join("course/problems"));
+        fs::write(root.join(COMPOSE_CONFIG), "").unwrap();
+        assert_eq!(root_of(&problem), root);
+        fs::write(root.join("course").join(COMPOSE_CONFIG), "").unwrap();
+        assert_eq!(root_of(&problem), root.join("course"));
+        fs::write(root.join(SETTINGS_FILE), "").unwrap();
+        assert_eq!(root_of(&problem), root);
+    }
+
+    #[test]
+    fn fails_without_root() {
+        let (_dir, root) = course();
+        let problem = root.join("course/problems/intro/add");
+        fs::write(problem.join(WORKSPACE_MANIFEST), "[package]\n").unwrap();
+        let err = Repository::from_path(&problem).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{problem:?} is not within a course repository, add {SETTINGS_FILE} to its root"
+            )
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use anyhow::{bail, Context, Result};
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// The environment variables kept in the sandbox, the rest (e.g. `TESTER_TOKEN`) are dropped.
+const KEPT_VARIABLES: [&str; 12] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TERM",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "RUST_BACKTRACE",
+    "PYTHONPATH",
+    "VIRTUAL_ENV",
+];
+
+/// Isolation of the commands running untrusted code: the file system is read-only but a private
+/// target directory and fresh `/tmp` and `HOME`, where only the course and the toolchains are
+/// kept. The network is loopback only, the processes outside the sandbox are hidden and
+/// the environment is cleared.
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    read_only: PathBuf,
+    target_dir: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(read_only: &Path, target_dir: PathBuf) -> Self {
+        Self {
+            read_only: read_only.to_path_buf(),
+            target_dir,
+        }
+    }
+
+    /// The `CARGO_TARGET_DIR` of the sandboxed commands.
+    pub fn get_target_dir(&self) -> &Path {
+        &self.target_dir
+    }
+
+    /// Fetches the dependencies of the crate in `workdir`, as there is no network in the sandbox
+    /// and the lockfile can't be written there.
+    pub fn prepare(&self, workdir: &Path) -> Result<()> {
+        if !workdir.join("Cargo.toml").is_file() {
+            return Ok(());
+        }
+        let output = process::Command::new("cargo")
+            .args(["fetch", "--quiet"])
+            .current_dir(workdir)
+            .output()
+            .context("failed to launch cargo fetch")?;
+        if !output.status.success() {
+            bail!(
+                "failed to fetch the dependencies for the sandbox:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+        }
+        Ok(())
+    }
+
+    fn clear_env(cmd: &mut process::Command) {
+        cmd.env_clear()
+            .envs(
+                env::vars_os().filter(|(name, _)| KEPT_VARIABLES.iter().any(|kept| name == *kept)),
+            )
+            .env("CARGO_NET_OFFLINE", "true");
+    }
+
+    /// The paths the commands need from the hidden directories: the course, the toolchains
+    /// and the programs on `PATH` along with their install prefixes.
+    #[cfg(target_os = "linux")]
+    fn kept_paths(&self, home: Option<&Path>) -> Vec<PathBuf> {
+        let var = |name| env::var_os(name).map(PathBuf::from);
+        let programs = var("PATH")
+            .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let prefixes = programs
+            .iter()
+            .map(|dir| dir.parent().unwrap_or(dir).to_path_buf())
+            .chain(var("VIRTUAL_ENV"));
+        [self.read_only.clone(), self.target_dir.clone()]
+            .into_iter()
+            .chain(var("CARGO_HOME").or_else(|| home.map(|home| home.join(".cargo"))))
+            .chain(var("RUSTUP_HOME").or_else(|| home.map(|home| home.join(".rustup"))))
+            .chain(prefixes)
+            .filter_map(|path| path.canonicalize().ok())
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    /// Makes the command enter the sandbox, must be called before setting its environment.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut process::Command) -> Result<()> {
+        use std::{fs, os::unix::process::CommandExt};
+
+        fs::create_dir_all(&self.target_dir)
+            .with_context(|| format!("failed to create {:?}", self.target_dir))?;
+        let target_dir = self
+            .target_dir
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize {:?}", self.target_dir))?;
+        let target_dir = c_string(&target_dir)?;
+        let mount_points = mount_points()?;
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let kept = self.kept_paths(home.as_deref());
+        let mut hidden = Vec::new();
+        for (root, options) in home
+            .iter()
+            .map(|home| (home.as_path(), c"mode=755"))
+            .chain([(Path::new("/tmp"), c"mode=1777")])
+        {
+            let root = match root.canonicalize() {
+                Ok(root) if root != Path::new("/") && root.is_dir() => root,
+                _ => continue,
+            };
+            if !hidden.iter().any(|hidden: &Hidden| hidden.root == root) {
+                hidden.push(Hidden::new(root, options, &kept)?);
+            }
+        }
+        let cwd = match cmd.get_current_dir().map(Path::canonicalize) {
+            Some(cwd) => Some(c_string(
+                &cwd.context("failed to canonicalize working directory")?,
+            )?),
+            None => None,
+        };
+        Self::clear_env(cmd);
+        // SAFETY: geteuid and getegid have no memory safety requirements
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        let uid_map = format!("{uid} {uid} 1");
+        let gid_map = format!("{gid} {gid} 1");
+        // SAFETY: the closure only makes syscalls on the data prepared beforehand and doesn't allocate
+        unsafe {
+            cmd.pre_exec(move || {
+                let namespaces = libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWPID;
+                if uid == 0 {
+                    check(libc::unshare(namespaces))?;
+                } else {
+                    // Unprivileged users get the capabilities to mount in their own namespace
+                    check(libc::unshare(libc::CLONE_NEWUSER | namespaces))?;
+                    write_file(c"/proc/self/setgroups", b"deny")?;
+                    write_file(c"/proc/self/uid_map", uid_map.as_bytes())?;
+                    write_file(c"/proc/self/gid_map", gid_map.as_bytes())?;
+                }
+                mount_read_only(&mount_points, &target_dir)?;
+                for hidden in &mut hidden {
+                    hidden.mount()?;
+                }
+                // The working directory was entered before the mounts, so it is entered anew
+                if let Some(cwd) = &cwd {
+                    check(libc::chdir(cwd.as_ptr()))?;
+                }
+                enter_pid_namespace()?;
+                loopback_up()
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _cmd: &mut process::Command) -> Result<()> {
+        bail!("sandbox is only supported on Linux")
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check(ret: libc::c_int) -> std::io::Result<()> {
+    if ret == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes the whole `data` to the file.
+#[cfg(target_os = "linux")]
+unsafe fn write_file(path: &std::ffi::CStr, data: &[u8]) -> std::io::Result<()> {
+    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    check(fd)?;
+    let written = libc::write(fd, data.as_ptr().cast(), data.len());
+    libc::close(fd);
+    if written != data.len() as isize {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn c_string(path: &Path) -> Result<CString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// The mount points with the escapes of `/proc/self/mountinfo` undone, but the ones of `/proc`,
+/// which is mounted anew, and `/dev`, which must stay writable.
+#[cfg(target_os = "linux")]
+fn mount_points() -> Result<Vec<CString>> {
+    use std::{ffi::OsString, fs, os::unix::ffi::OsStringExt};
+
+    let text =
+        fs::read_to_string("/proc/self/mountinfo").context("failed to read the mount points")?;
+    let mut mount_points = Vec::new();
+    for escaped in text.lines().filter_map(|line| line.split(' ').nth(4)) {
+        let mut path = Vec::with_capacity(escaped.len());
+        let mut rest = escaped.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            let code = tail
+                .get(..3)
+                .and_then(|code| u8::from_str_radix(std::str::from_utf8(code).ok()?, 8).ok());
+            match code {
+                Some(code) if byte == b'\\' => {
+                    path.push(code);
+                    rest = &tail[3..];
+                }
+                _ => {
+                    path.push(byte);
+                    rest = tail;
+                }
+            }
+        }
+        let path = PathBuf::from(OsString::from_vec(path));
+        if !path.starts_with("/proc") && !path.starts_with("/dev") {
+            mount_points.push(c_string(&path)?);
+        }
+    }
+    Ok(mount_points)
+}
+
+/// A directory hidden under a tmpfs, with the kept paths inside it bound back.
+#[cfg(target_os = "linux")]
+struct Hidden {
+    root: PathBuf,
+    c_root: CString,
+    options: &'static CStr,
+    kept: Vec<CString>,
+    /// The directories to create in the tmpfs for the binds, parents first.
+    dirs: Vec<CString>,
+    /// The kept paths opened before the tmpfs covers them.
+    fds: Vec<libc::c_int>,
+}
+
+#[cfg(target_os = "linux")]
+impl Hidden {
+    fn new(root: PathBuf, options: &'static CStr, kept: &[PathBuf]) -> Result<Self> {
+        let mut inside: Vec<_> = kept
+            .iter()
+            .filter(|path| path.starts_with(&root) && **path != root)
+            .collect();
+        inside.sort();
+        // The paths inside the other kept ones come along with them
+        inside.dedup_by(|path, outer| path.starts_with(outer));
+        let mut dirs = Vec::new();
+        for path in &inside {
+            let mut parents: Vec<_> = path.ancestors().take_while(|dir| *dir != root).collect();
+            parents.reverse();
+            for dir in parents {
+                let dir = c_string(dir)?;
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+        Ok(Self {
+            c_root: c_string(&root)?,
+            root,
+            options,
+            fds: vec![-1; inside.len()],
+            kept: inside
+                .into_iter()
+                .map(|path| c_string(path))
+                .collect::<Result<_>>()?,
+            dirs,
+        })
+    }
+
+    /// Mounts the tmpfs and binds the kept paths back from under it.
+    unsafe fn mount(&mut self) -> std::io::Result<()> {
+        use std::ptr::null;
+
+        for (path, fd) in self.kept.iter().zip(&mut self.fds) {
+            *fd = libc::open(
+                path.as_ptr(),
+                libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC,
+            );
+            check(*fd)?;
+        }
+        check(libc::mount(
+            c"tmpfs".as_ptr(),
+            self.c_root.as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            self.options.as_ptr().cast(),
+        ))?;
+        for dir in &self.dirs {
+            if libc::mkdir(dir.as_ptr(), 0o755) == -1 && *libc::__errno_location() != libc::EEXIST {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        for (path, &fd) in self.kept.iter().zip(&self.fds) {
+            let mut buffer = [0; 32];
+            check(libc::mount(
+                fd_path(fd, &mut buffer).as_ptr(),
+                path.as_ptr(),
+                null(),
+                libc::MS_BIND | libc::MS_REC,
+                null(),
+            ))?;
+            libc::close(fd);
+        }
+        Ok(())
+    }
+}
+
+/// Writes `/proc/self/fd/<fd>` to the buffer, as nothing may be allocated before exec.
+#[cfg(target_os = "linux")]
+fn fd_path(fd: libc::c_int, buffer: &mut [u8; 32]) -> &CStr {
+    const PREFIX: &[u8] = b"/proc/self/fd/";
+    buffer[..PREFIX.len()].copy_from_slice(PREFIX);
+    let mut digits = 1;
+    while fd as u64 / 10u64.pow(digits) > 0 {
+        digits += 1;
+    }
+    for i in 0..digits {
+        let digit = fd as u64 / 10u64.pow(digits - 1 - i) % 10;
+        buffer[PREFIX.len() + i as usize] = b'0' + digit as u8;
+    }
+    buffer[PREFIX.len() + digits as usize] = 0;
+    CStr::from_bytes_until_nul(buffer).unwrap_or(c"/")
+}
+
+/// Remounts the path read-only, keeping the flags which are locked in a user namespace.
+#[cfg(target_os = "linux")]
+unsafe fn remount_read_only(path: &CStr) -> std::io::Result<()> {
+    // statvfs reports the flags with the same values as the mount flags
+    let mut stat: libc::statvfs = std::mem::zeroed();
+    check(libc::statvfs(path.as_ptr(), &mut stat))?;
+    let locked = stat.f_flag
+        & (libc::MS_NOSUID
+            | libc::MS_NODEV
+            | libc::MS_NOEXEC
+            | libc::MS_NOATIME
+            | libc::MS_NODIRATIME
+            | libc::MS_RELATIME);
+    check(libc::mount(
+        std::ptr::null(),
+        path.as_ptr(),
+        std::ptr::null(),
+        libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | locked,
+        std::ptr::null(),
+    ))
+}
+
+/// Remounts every mount point read-only in the new mount namespace, but `target_dir`.
+#[cfg(target_os = "linux")]
+unsafe fn mount_read_only(mount_points: &[CString], target_dir: &CStr) -> std::io::Result<()> {
+    use std::ptr::null;
+
+    // Keep the mounts from propagating back to the parent namespace
+    check(libc::mount(
+        null(),
+        c"/".as_ptr(),
+        null(),
+        libc::MS_REC | libc::MS_PRIVATE,
+        null(),
+    ))?;
+    // The writable bind is a mount of its own, so the remounts below leave it writable
+    check(libc::mount(
+        target_dir.as_ptr(),
+        target_dir.as_ptr(),
+        null(),
+        libc::MS_BIND,
+        null(),
+    ))?;
+    for mount_point in mount_points {
+        match remount_read_only(mount_point) {
+            // Covered by another mount or not reachable by the user, so not writable either
+            Err(err) if matches!(err.raw_os_error(), Some(libc::ENOENT | libc::EACCES)) => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Forks into the new PID namespace and mounts its own `/proc` there, so that the command
+/// can't read the environment of rover or other processes through `/proc/<pid>/environ`.
+/// The parent stays outside, waits for the command and exits with its status.
+#[cfg(target_os = "linux")]
+unsafe fn enter_pid_namespace() -> std::io::Result<()> {
+    use std::ptr::null;
+
+    let pid = libc::fork();
+    check(pid)?;
+    if pid == 0 {
+        // The command is the init of the namespace, it must not outlive the waiting parent
+        check(libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL))?;
+        return check(libc::mount(
+            c"proc".as_ptr(),
+            c"/proc".as_ptr(),
+            c"proc".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+            null(),
+        ));
+    }
+    // The parent never execs, so it closes its descriptors itself, otherwise the spawning
+    // process would wait for the close-on-exec error pipe until the command exits
+    if libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0) == -1 {
+        for fd in 0..1024 {
+            libc::close(fd);
+        }
+    }
+    let mut status = 0;
+    while libc::waitpid(pid, &mut status, 0) == -1 {
+        if *libc::__errno_location() != libc::EINTR {
+            libc::_exit(127);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+/// Brings up the loopback interface of the new network namespace, it is the only one there.
+#[cfg(target_os = "linux")]
+unsafe fn loopback_up() -> std::io::Result<()> {
+    let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+    check(fd)?;
+    let mut request: libc::ifreq = std::mem::zeroed();
+    for (dst, src) in request.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+    request.ifr_ifru.ifru_flags = (libc::IFF_UP | libc::IFF_RUNNING) as libc::c_short;
+    let ret = libc::ioctl(fd, libc::SIOCSIFFLAGS, &request);
+    libc::close(fd);
+    check(ret)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::{fs, net::TcpListener};
+
+    fn sandbox() -> (tempfile::TempDir, Sandbox) {
+        let dir = tempfile::tempdir().unwrap();
+        let course = dir.path().join("course");
+        fs::create_dir(&course).unwrap();
+        let sandbox = Sandbox::new(&course, course.join("target"));
+        (dir, sandbox)
+    }
+
+    /// Runs the bash script in the sandbox, `None` if the sandbox can't be set up here.
+    fn run(sandbox: &Sandbox, script: &str) -> Option<process::Output> {
+        let mut cmd = process::Command::new("bash");
+        cmd.args(["-c", script]).current_dir(&sandbox.read_only);
+        sandbox.apply(&mut cmd).unwrap();
+        match cmd.output() {
+            Ok(output) => Some(output),
+            Err(err) => {
+                eprintln!("skipping the sandbox test, namespaces are unavailable: {err}");
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn network_is_unreachable() {
+        let (_dir, sandbox) = sandbox();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let script = format!("exec 3<>/dev/tcp/127.0.0.1/{port} && echo connected");
+        if let Some(output) = run(&sandbox, &script) {
+            assert!(!output.status.success(), "{output:?}");
+            assert!(output.stdout.is_empty());
+        }
+    }
+
+    #[test]
+    fn loopback_works() {
+        let (_dir, sandbox) = sandbox();
+        let script = "python3 -c 'import socket\n\
+            server = socket.create_server((\"127.0.0.1\", 0))\n\
+            client = socket.create_connection(server.getsockname())\n\
+            server.accept()[0].sendall(b\"pong\")\n\
+            print(client.recv(4).decode())'";
+        if let Some(output) = run(&sandbox, script) {
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                "pong\n",
+                "{output:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn host_is_read_only_and_tmp_is_private() {
+        let (dir, sandbox) = sandbox();
+        let secret = dir.path().join("secret");
+        fs::write(&secret, "token").unwrap();
+        let scratch = env::temp_dir().join(format!("rover-sandbox-{}", process::id()));
+        let script = format!(
+            "touch /rover-sandbox-test && exit 1; test -e {secret:?} && exit 2; \
+             touch {scratch:?} && ls $HOME > /dev/null && echo ok"
+        );
+        if let Some(output) = run(&sandbox, &script) {
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                "ok\n",
+                "{output:?}"
+            );
+            assert!(!scratch.exists());
+        }
+    }
+
+    #[test]
+    fn course_is_read_only() {
+        let (dir, sandbox) = sandbox();
+        let course = dir.path().join("course");
+        let script = format!(
+            "touch {:?}/Cargo.toml && exit 1; touch {:?}/build && echo written",
+            course,
+            sandbox.get_target_dir()
+        );
+        if let Some(output) = run(&sandbox, &script) {
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "written\n");
+            assert!(!course.join("Cargo.toml").exists());
+            assert!(sandbox.get_target_dir().join("build").is_file());
+        }
+    }
+
+    #[test]
+    fn outside_processes_are_hidden() {
+        let (_dir, sandbox) = sandbox();
+        let script = format!(
+            "echo $$; test -e /proc/{}/environ && echo visible; true",
+            process::id()
+        );
+        if let Some(output) = run(&sandbox, &script) {
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+            assert!(output.status.success());
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use super::step::Step;
+use crate::testing::{
+    libtest::TestOutcome,
+    result::{CommandResult, Outcome, StepResult, TestingResult},
+};
+use anyhow::{bail, Result};
+use glob::Pattern;
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{collections::BTreeMap, fmt};
+
+const DEFAULT_WEIGHT: f64 = 1.0;
+
+/// The `scoring` section of the problem config.
+///
+/// The score of a step is the weighted share of the passed tests of its commands.
+/// Any other failure of a command, e.g. of a linter, a crash, a timeout or a skip,
+/// makes the score of the whole step zero, and a step without tests scores all or nothing.
+/// The score of a problem is the weighted mean of the scores of its steps.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Scoring {
+    #[serde(default)]
+    steps: BTreeMap<String, f64>,
+    /// Weights of the tests by the name patterns, the first matching one is used.
+    #[serde(default, deserialize_with = "deserialize_test_weights")]
+    tests: Vec<(Pattern, f64)>,
+}
+
+fn deserialize_test_weights<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(Pattern, f64)>, D::Error> {
+    struct TestWeightsVisitor;
+
+    impl<'de> Visitor<'de> for TestWeightsVisitor {
+        type Value = Vec<(Pattern, f64)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a mapping from test name patterns to weights")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut weights = Vec::new();
+            while let Some((pattern, weight)) = map.next_entry::<String, f64>()? {
+                let pattern = Pattern::new(&pattern).map_err(|err| {
+                    de::Error::custom(format!("invalid test pattern {pattern:?}: {err}"))
+                })?;
+                weights.push((pattern, weight));
+            }
+            Ok(weights)
+        }
+    }
+
+    deserializer.deserialize_map(TestWeightsVisitor)
+}
+
+fn is_valid_weight(weight: f64) -> bool {
+    weight.is_finite() && weight >= 0.0
+}
+
+impl Scoring {
+    pub fn validate(&self, steps: &[Step]) -> Result<()> {
+        for (name, weight) in &self.steps {
+            if !steps.iter().any(|step| step.name() == name) {
+                bail!("scoring refers to unknown step \"{name}\"")
+            }
+            if !is_valid_weight(*weight) {
+                bail!("weight of step \"{name}\" must be a non-negative number")
+            }
+        }
+        for (pattern, weight) in &self.tests {
+            if !is_valid_weight(*weight) {
+                bail!("weight of tests \"{pattern}\" must be a non-negative number")
+            }
+        }
+        Ok(())
+    }
+
+    fn test_weight(&self, name: &str) -> f64 {
+        self.tests
+            .iter()
+            .find(|(pattern, _)| pattern.matches(name))
+            .map_or(DEFAULT_WEIGHT, |(_, weight)| *weight)
+    }
+
+    /// Returns the passed and the total weight of the tests of the command, or `None`
+    /// if the command failed as a whole.
+    fn command_weights(&self, command: &CommandResult) -> Option<(f64, f64)> {
+        let failed_tests = command
+            .tests
+            .iter()
+            .any(|test| test.outcome == TestOutcome::Failed);
+        // Tests can't be trusted if the command failed for other reasons, e.g. crashed or timed out
+        if command.outcome != Outcome::Passed
+            && !(command.outcome == Outcome::Failed && failed_tests)
+        {
+            return None;
+        }
+        let mut passed = 0.0;
+        let mut total = 0.0;
+        for test in &command.tests {
+            let weight = self.test_weight(&test.name);
+            match test.outcome {
+                TestOutcome::Passed => {
+                    passed += weight;
+                    total += weight;
+                }
+                TestOutcome::Failed => total += weight,
+                TestOutcome::Ignored => {}
+            }
+        }
+        Some((passed, total))
+    }
+
+    fn step_score(&self, step: &StepResult) -> f64 {
+        let mut passed = 0.0;
+        let mut total = 0.0;
+        for command in &step.commands {
+            match self.command_weights(command) {
+                Some((p, t)) => {
+                    passed += p;
+                    total += t;
+                }
+                None => return 0.0,
+            }
+        }
+        if total > 0.0 {
+            passed / total
+        } else {
+            1.0
+        }
+    }
+
+    /// Sets the scores of the steps and of the whole testing result.
+    pub fn apply(&self, result: &mut TestingResult) {
+        let mut scored = 0.0;
+        let mut total = 0.0;
+        for step in &mut result.steps {
+            step.score = self.step_score(step);
+            let weight = self
+                .steps
+                .get(&step.name)
+                .copied()
+                .unwrap_or(DEFAULT_WEIGHT);
+            scored += weight * step.score;
+            total += weight;
+        }
+        result.score = if total > 0.0 { scored / total } else { 1.0 };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::libtest::TestCase;
+
+    fn scoring(yml: &str) -> Scoring {
+        serde_yaml::from_str(yml).unwrap()
+    }
+
+    fn command(outcome: Outcome, tests: &[(&str, TestOutcome)]) -> CommandResult {
+        let mut command = CommandResult::skipped("cargo-test".to_string());
+        command.outcome = outcome;
+        command.tests = tests
+            .iter()
+            .map(|(name, outcome)| TestCase {
+                name: name.to_string(),
+                outcome: *outcome,
+            })
+            .collect();
+        command
+    }
+
+    fn step(name: &str, commands: Vec<CommandResult>) -> StepResult {
+        StepResult::new(name.to_string(), commands)
+    }
+
+    fn score(scoring: &Scoring, steps: Vec<StepResult>) -> TestingResult {
+        let mut result = TestingResult::new("intro/add".to_string(), steps);
+        scoring.apply(&mut result);
+        result
+    }
+
+    #[test]
+    fn weights() {
+        let scoring = scoring("steps: {tests: 3}\ntests: {\"big_*\": 2, \"flaky\": 0}\n");
+        let tests = command(
+            Outcome::Failed,
+            &[
+                ("small", TestOutcome::Passed),
+                ("big_sum", TestOutcome::Passed),
+                ("big_product", TestOutcome::Failed),
+                ("flaky", TestOutcome::Failed),
+                ("slow", TestOutcome::Ignored),
+            ],
+        );
+        let result = score(
+            &scoring,
+            vec![
+                step("tests", vec![tests]),
+                step("lint", vec![command(Outcome::Passed, &[])]),
+            ],
+        );
+        assert_eq!(result.steps[0].score, 0.6);
+        assert_eq!(result.steps[1].score, 1.0);
+        assert_eq!(result.score, (3.0 * 0.6 + 1.0) / 4.0);
+    }
+
+    #[test]
+    fn failed_commands_fail_the_step() {
+        let scoring = Scoring::default();
+        let passed = || command(Outcome::Passed, &[("a", TestOutcome::Passed)]);
+        let result = score(
+            &scoring,
+            vec![
+                step(
+                    "lint",
+                    vec![command(Outcome::Passed, &[]), command(Outcome::Failed, &[])],
+                ),
+                step(
+                    "tests",
+                    vec![
+                        passed(),
+                        command(Outcome::TimedOut, &[("b", TestOutcome::Failed)]),
+                    ],
+                ),
+                step(
+                    "crash",
+                    vec![
+                        passed(),
+                        command(Outcome::Failed, &[("b", TestOutcome::Passed)]),
+                    ],
+                ),
+                step("skipped", vec![command(Outcome::Skipped, &[])]),
+            ],
+        );
+        for step in &result.steps {
+            assert_eq!(step.score, 0.0, "{}", step.name);
+        }
+        assert_eq!(result.score, 0.0);
+    }
+
+    #[test]
+    fn invalid_weights() {
+        let steps = [Step::new("tests".to_string(), Vec::new(), None)];
+        scoring("steps: {tests: 0}\ntests: {\"*\": 1.5}")
+            .validate(&steps)
+            .unwrap();
+        for yml in [
+            "steps: {tests: -1}",
+            "steps: {tests: .nan}",
+            "steps: {lint: 1}",
+            "tests: {\"big_*\": -2}",
+            "tests: {\"big_*\": .inf}",
+        ] {
+            assert!(scoring(yml).validate(&steps).is_err(), "{yml} is accepted");
+        }
+        assert!(serde_yaml::from_str::<Scoring>("tests: {\"[\": 1}").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The settings of the course repository, `.rover.toml` in its root.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct Settings {
+    /// The folder with `GROUP/TITLE` problems, relative to the course repository.
+    problems_folder: PathBuf,
+    /// The solutions repository, relative to the course repository.
+    solutions_repo: PathBuf,
+    /// The branch of a problem in the solutions repository, with `{group}` and `{title}`.
+    branch_name: String,
+    /// Set by compose in the public repository, where every problem must have its manifest
+    /// of the protected files.
+    composed: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            problems_folder: PathBuf::from("problems"),
+            solutions_repo: PathBuf::from("../solutions"),
+            branch_name: "{group}/{title}".to_string(),
+            composed: false,
+        }
+    }
+}
+
+enum Part<'a> {
+    Literal(&'a str),
+    Group,
+    Title,
+}
+
+/// Splits the branch name template into literals and placeholders.
+fn parse_template(template: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(Part::Literal(&rest[..start]));
+        }
+        let end = rest[start..]
+            .find('}')
+            .context("unclosed '{' in branch-name")?
+            + start;
+        parts.push(match &rest[start + 1..end] {
+            "group" => Part::Group,
+            "title" => Part::Title,
+            name => bail!("unknown placeholder {{{name}}} in branch-name"),
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest));
+    }
+    Ok(parts)
+}
+
+/// Matches the branch against the template parts, placeholders match non-empty names.
+fn matches(parts: &[Part], branch: &str) -> bool {
+    match parts.split_first() {
+        None => branch.is_empty(),
+        Some((Part::Literal(literal), rest)) => branch
+            .strip_prefix(literal)
+            .is_some_and(|branch| matches(rest, branch)),
+        Some((_, rest)) => branch
+            .char_indices()
+            .skip(1)
+            .map(|(i, _)| i)
+            .chain([branch.len()])
+            .take_while(|&i| !branch[..i].contains('/'))
+            .any(|i| !branch.is_empty() && matches(rest, &branch[i..])),
+    }
+}
+
+impl Settings {
+    pub fn from_toml(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        let settings: Self =
+            toml::from_str(&text).with_context(|| format!("invalid settings {path:?}"))?;
+        let parts = parse_template(&settings.branch_name)
+            .with_context(|| format!("invalid settings {path:?}"))?;
+        if !parts.iter().any(|part| matches!(part, Part::Title)) {
+            bail!("branch-name in {path:?} must contain {{title}}")
+        }
+        if settings
+            .problems_folder
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            bail!(
+                "problems-folder in {path:?} must be a relative path inside the course repository"
+            )
+        }
+        Ok(settings)
+    }
+
+    pub fn get_problems_folder(&self) -> &Path {
+        &self.problems_folder
+    }
+
+    pub fn get_solutions_repo(&self) -> &Path {
+        &self.solutions_repo
+    }
+
+    pub fn is_composed(&self) -> bool {
+        self.composed
+    }
+
+    pub fn branch_name(&self, group: &str, title: &str) -> String {
+        self.branch_name
+            .replace("{group}", group)
+            .replace("{title}", title)
+    }
+
+    /// Whether the branch is named like a problem branch.
+    pub fn is_problem_branch(&self, branch: &str) -> bool {
+        parse_template(&self.branch_name).is_ok_and(|parts| matches(&parts, branch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_names() {
+        let settings = Settings::default();
+        assert_eq!(settings.branch_name("intro", "conway"), "intro/conway");
+        assert!(settings.is_problem_branch("intro/conway"));
+        assert!(!settings.is_problem_branch("main"));
+        assert!(!settings.is_problem_branch("a/b/c"));
+
+        let settings = Settings {
+            branch_name: "hw-{title}".to_string(),
+            ..Settings::default()
+        };
+        assert_eq!(settings.branch_name("intro", "conway"), "hw-conway");
+        assert!(settings.is_problem_branch("hw-min-queue"));
+        assert!(!settings.is_problem_branch("hw-"));
+        assert!(!settings.is_problem_branch("feature/hw-x"));
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rover.toml");
+        for text in [
+            "branch-name = \"{group}\"",
+            "branch-name = \"{title\"",
+            "branch-name = \"{user}/{title}\"",
+            "problems = \"tasks\"",
+            "problems-folder = \"/problems\"",
+            "problems-folder = \"../problems\"",
+            "problems-folder = \"tasks/../..\"",
+        ] {
+            fs::write(&path, text).unwrap();
+            assert!(Settings::from_toml(&path).is_err(), "{text} is accepted");
+        }
+        fs::write(
+            &path,
+            "problems-folder = \"tasks\"\nbranch-name = \"{group}-{title}\"",
+        )
+        .unwrap();
+        let settings = Settings::from_toml(&path).unwrap();
+        assert_eq!(settings.get_problems_folder(), Path::new("tasks"));
+        assert!(settings.is_problem_branch("intro-conway"));
+    }
+}
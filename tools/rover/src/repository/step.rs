@@ -1,23 +1,232 @@
-use super::command::Command;
+use super::command::{Command, CommandEntry};
+use crate::util::duration::deserialize_duration;
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{collections::BTreeSet, fmt, time::Duration};
+
+#[derive(Debug)]
+pub struct StepCommand {
+    pub command: Command,
+    pub timeout: Option<Duration>,
+}
+
+/// A command is written either as a name of a built-in command, as a mapping
+/// with the `builtin` key to set its options, or as a custom command mapping.
+impl<'de> Deserialize<'de> for StepCommand {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StepCommandVisitor;
+
+        impl<'de> Visitor<'de> for StepCommandVisitor {
+            type Value = StepCommand;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a name of a built-in command or a command mapping")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<StepCommand, E> {
+                Ok(StepCommand {
+                    command: Command::from_name(name).map_err(E::custom)?,
+                    timeout: None,
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<StepCommand, A::Error> {
+                let entry = CommandEntry::deserialize(MapAccessDeserializer::new(map))?;
+                let (command, timeout) = entry.into_command().map_err(de::Error::custom)?;
+                Ok(StepCommand { command, timeout })
+            }
+        }
+
+        deserializer.deserialize_any(StepCommandVisitor)
+    }
+}
 
 #[derive(Debug)]
 pub struct Step {
-    #[allow(unused)]
     name: String,
-    commands: Vec<Command>,
+    commands: Vec<StepCommand>,
+    timeout: Option<Duration>,
 }
 
 impl Step {
-    pub fn new(name: String, commands: Vec<Command>) -> Self {
-        Self { name, commands }
+    pub fn new(name: String, commands: Vec<StepCommand>, timeout: Option<Duration>) -> Self {
+        Self {
+            name,
+            commands,
+            timeout,
+        }
     }
 
-    #[allow(unused)]
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn commands(&self) -> &[Command] {
+    pub fn commands(&self) -> &[StepCommand] {
         self.commands.as_slice()
     }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// A step written as a mapping to set its options.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StepBody {
+    commands: Vec<StepCommand>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    timeout: Option<Duration>,
+}
+
+/// A step is written either as a list of commands or as a [`StepBody`].
+fn deserialize_step_body<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StepBody, D::Error> {
+    struct StepBodyVisitor;
+
+    impl<'de> Visitor<'de> for StepBodyVisitor {
+        type Value = StepBody;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of commands or a mapping with commands")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<StepBody, A::Error> {
+            let mut commands = Vec::new();
+            while let Some(command) = seq.next_element()? {
+                commands.push(command);
+            }
+            Ok(StepBody {
+                commands,
+                timeout: None,
+            })
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<StepBody, A::Error> {
+            StepBody::deserialize(MapAccessDeserializer::new(map))
+        }
+    }
+
+    deserializer.deserialize_any(StepBodyVisitor)
+}
+
+struct StepSeed(String);
+
+impl<'de> de::DeserializeSeed<'de> for StepSeed {
+    type Value = Step;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Step, D::Error> {
+        let body = deserialize_step_body(deserializer)?;
+        Ok(Step::new(self.0, body.commands, body.timeout))
+    }
+}
+
+/// Deserializes the `steps` mapping keeping the order of the steps.
+pub fn deserialize_steps<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Step>, D::Error> {
+    struct StepsVisitor;
+
+    impl<'de> Visitor<'de> for StepsVisitor {
+        type Value = Vec<Step>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a mapping from step names to steps")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<Step>, A::Error> {
+            let mut names = BTreeSet::new();
+            let mut steps = Vec::new();
+            while let Some(name) = map.next_key::<String>()? {
+                if !names.insert(name.clone()) {
+                    return Err(de::Error::custom(format!("duplicate step \"{name}\"")));
+                }
+                steps.push(map.next_value_seed(StepSeed(name))?);
+            }
+            Ok(steps)
+        }
+    }
+
+    deserializer.deserialize_map(StepsVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::Config;
+    use std::fs;
+
+    fn config_error(yml: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".config.yml");
+        fs::write(&path, yml).unwrap();
+        let err = Config::from_yml(&path).unwrap_err();
+        // Skip the context with the temporary path
+        format!("{:#}", err.root_cause())
+    }
+
+    #[test]
+    fn errors_point_at_wrong_keys() {
+        assert_eq!(
+            config_error(
+                "steps:\n  tests:\n    - cargo-test\n    - program: x\n      shell: true\n"
+            ),
+            "steps.tests[1]: unknown field `shell`, expected one of `builtin`, `name`, \
+             `program`, `args`, `env`, `cwd`, `expect`, `timeout` at line 5 column 7"
+        );
+        assert_eq!(
+            config_error("steps:\n  tests:\n    commands: [cargo-test]\n    timout: 1s\n"),
+            "steps.tests: unknown field `timout`, expected `commands` or `timeout` \
+             at line 4 column 5"
+        );
+        assert_eq!(
+            config_error("steps: {tests: [cargo-test]}\nstepz: {}\n"),
+            "unknown field `stepz`, expected one of `version`, `toolchain`, `allowed-patterns`, \
+             `steps`, `forbid`, `limits`, `scoring` at line 2 column 1"
+        );
+    }
+
+    #[test]
+    fn errors_describe_wrong_types() {
+        assert_eq!(
+            config_error("steps: {tests: [cargo-test]}\nlimits:\n  memory: [1]\n"),
+            "limits.memory: invalid type: sequence, expected a number of bytes \
+             or a size like \"256M\" at line 3 column 11"
+        );
+        assert_eq!(
+            config_error("steps:\n  tests:\n    - {builtin: cargo-test, timeout: -1}\n"),
+            "steps.tests[0].timeout: invalid type: integer `-1`, expected a number of seconds \
+             or a duration like \"500ms\" or \"5m\" at line 3 column 38"
+        );
+        assert_eq!(
+            config_error("steps:\n  tests: cargo-test\n"),
+            "steps.tests: invalid type: string \"cargo-test\", expected a list of commands \
+             or a mapping with commands at line 2 column 10"
+        );
+        assert!(
+            config_error("steps:\n  tests:\n    - {builtin: cargo-test, args: [x]}\n").starts_with(
+                "steps.tests[0]: built-in command \"cargo-test\" can only have a timeout"
+            )
+        );
+    }
+
+    #[test]
+    fn steps_keep_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".config.yml");
+        fs::write(
+            &path,
+            "steps:\n  lint: [cargo-fmt, {builtin: cargo-clippy, timeout: 1m}]\n  \
+             tests: {commands: [cargo-test], timeout: 90}\n",
+        )
+        .unwrap();
+        let config = Config::from_yml(&path).unwrap();
+        let steps = config.get_steps();
+        assert_eq!(steps[0].name(), "lint");
+        assert_eq!(steps[0].commands().len(), 2);
+        assert_eq!(steps[0].commands()[1].timeout.unwrap().as_secs(), 60);
+        assert_eq!(steps[1].name(), "tests");
+        assert_eq!(steps[1].timeout().unwrap().as_secs(), 90);
+        assert!(config_error("steps:\n  a: []\n  a: []\n").contains("duplicate step \"a\""));
+    }
 }
@@ -1,13 +1,25 @@
-use super::{command::Command, context::CommandContext};
-use anyhow::{bail, Context, Result};
+use super::{
+    command::{Command, Expectation},
+    context::CommandContext,
+    execution::{execute, Execution},
+    inspect::{PathUsage, SourceFile, Violation},
+    policy::{check_manifest, check_source},
+};
+use crate::testing::{
+    libtest::parse_test_output,
+    result::{CommandResult, Outcome},
+};
+use anyhow::{bail, Result};
+use serde::{de, Deserialize, Deserializer};
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::BTreeMap,
+    path::Path,
     process,
+    time::{Duration, Instant},
 };
 
-const FORBID_UNSAFE_LINE: &str = "#![forbid(unsafe_code)]";
-const FORBID_STD_LINE: &str = "#![no_std]";
+const FORBID_UNSAFE_ATTRIBUTE: &str = "#![forbid(unsafe_code)]";
+const FORBID_STD_ATTRIBUTE: &str = "#![no_std]";
 const FORBID_COLLECTIONS_PATTERNS: [&str; 8] = [
     "BTreeMap",
     "BTreeSet",
@@ -19,31 +31,6 @@ const FORBID_COLLECTIONS_PATTERNS: [&str; 8] = [
     "BinaryHeap",
 ];
 
-macro_rules! launch {
-    ($toolchain: expr, $command: expr, $context: expr) => {{
-        let toolchain_shell_line = $toolchain.get_shell_line()?;
-        let command_shell_line = $command.get_shell_line()?;
-        let toolchain_shell_iter = toolchain_shell_line.split(' ');
-        let command_shell_iter = command_shell_line.split(' ');
-        let mut iter = toolchain_shell_iter.chain(command_shell_iter);
-        let mut cmd = if let Some(program) = iter.next() {
-            let mut cmd = process::Command::new(program);
-            cmd.current_dir($context.get_workdir());
-            cmd
-        } else {
-            bail!("toolchain and command are empty")
-        };
-        while let Some(arg) = iter.next() {
-            cmd.arg(arg);
-        }
-        if cmd.status().context("command failed")?.success() {
-            Ok(())
-        } else {
-            bail!("command failed")
-        }
-    }};
-}
-
 #[derive(Clone, Copy, Debug)]
 pub enum Toolchain {
     Empty,
@@ -51,6 +38,12 @@ pub enum Toolchain {
     Nightly,
 }
 
+impl<'de> Deserialize<'de> for Toolchain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_name(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
 impl Toolchain {
     pub fn from_name(name: &str) -> Result<Self> {
         Ok(match name {
@@ -61,6 +54,15 @@ impl Toolchain {
         })
     }
 
+    /// The name of the toolchain for rustup, if it is managed by rustup.
+    pub fn rustup_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Empty => None,
+            Self::Stable => Some("stable"),
+            Self::Nightly => Some("nightly"),
+        }
+    }
+
     pub fn get_shell_line(&self) -> Result<String> {
         Ok(match self {
             Self::Empty => "".to_string(),
@@ -69,139 +71,151 @@ impl Toolchain {
         })
     }
 
-    pub fn run_command(&self, command: &Command, context: &CommandContext) -> Result<()> {
+    fn launch(
+        &self,
+        shell_line: &[&str],
+        context: &CommandContext,
+        workdir: &Path,
+        env: &BTreeMap<String, String>,
+        timeout: Option<Duration>,
+    ) -> Result<Execution> {
+        let options = context.get_options();
+        let toolchain_shell_line = self.get_shell_line()?;
+        let mut iter = toolchain_shell_line
+            .split(' ')
+            .filter(|arg| !arg.is_empty())
+            .chain(shell_line.iter().copied());
+        let mut cmd = if let Some(program) = iter.next() {
+            let mut cmd = process::Command::new(program);
+            cmd.current_dir(workdir);
+            cmd
+        } else {
+            bail!("toolchain and command are empty")
+        };
+        cmd.args(iter);
+        if let Some(sandbox) = &options.sandbox {
+            sandbox.apply(&mut cmd)?;
+            cmd.env("CARGO_TARGET_DIR", sandbox.get_target_dir());
+        } else if let Some(target_dir) = &options.target_dir {
+            cmd.env("CARGO_TARGET_DIR", target_dir);
+        }
+        cmd.envs(env);
+        context.get_limits().apply(&mut cmd);
+        execute(&mut cmd, timeout, !options.quiet, options.cancel.as_deref())
+    }
+
+    pub fn run_command(
+        &self,
+        command: &Command,
+        timeout: Option<Duration>,
+        context: &CommandContext,
+    ) -> Result<CommandResult> {
+        let start = Instant::now();
+        let mut result = CommandResult {
+            name: command.name().to_string(),
+            command: command.name().to_string(),
+            duration: Default::default(),
+            outcome: Outcome::Passed,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            tests: Vec::new(),
+        };
         match command {
             Command::ForbidUnsafe => {
-                for file in context.get_user_files() {
-                    if let Some(line) = BufReader::new(File::open(file)?)
-                        .lines()
-                        .next()
-                        .transpose()?
+                result.stderr = check_sources(context, |source| {
+                    let mut violations = Vec::new();
+                    if !source.is_empty()
+                        && !source.has_inner_attribute("forbid", Some("unsafe_code"))
                     {
-                        if line != FORBID_UNSAFE_LINE {
-                            bail!(format!(
-                                "file {file:?} does not contain line '{FORBID_UNSAFE_LINE}'"
-                            ))
-                        }
-                    } else {
-                        // TODO: ForbidUnsafe shouldn't check whether file is empty
-                        bail!(format!("file {file:?} is empty"))
+                        violations.push(source.violation(
+                            1,
+                            format!("file does not contain '{FORBID_UNSAFE_ATTRIBUTE}'"),
+                        ));
                     }
-                }
-                Ok(())
+                    violations.extend(source.unsafe_usages());
+                    violations
+                });
             }
             Command::ForbidCollections => {
-                for file in context.get_user_files() {
-                    for line in BufReader::new(File::open(file)?).lines() {
-                        let line = line.context("error reading file line")?;
-                        for pattern in FORBID_COLLECTIONS_PATTERNS {
-                            if line.contains(pattern) {
-                                bail!(format!("file {file:?} contains line '{pattern}'"))
-                            }
-                        }
-                    }
-                }
-                Ok(())
+                result.stderr = check_sources(context, |source| {
+                    source
+                        .path_usages()
+                        .into_iter()
+                        .filter(is_collection_usage)
+                        .map(|usage| {
+                            let message = format!("use of '{}' is not allowed", usage.canonical());
+                            source.violation(usage.line, message)
+                        })
+                        .collect()
+                });
             }
             Command::ForbidStd => {
-                for file in context.get_user_files() {
-                    if let Some(line) = BufReader::new(File::open(file)?)
-                        .lines()
-                        .next()
-                        .transpose()?
-                    {
-                        if line != FORBID_STD_LINE {
-                            bail!(format!(
-                                "file {file:?} does not contain line '{FORBID_STD_LINE}'"
-                            ))
+                result.stderr = check_sources(context, |source| {
+                    let mut violations = Vec::new();
+                    if !source.is_empty() && !source.has_inner_attribute("no_std", None) {
+                        violations.push(source.violation(
+                            1,
+                            format!("file does not contain '{FORBID_STD_ATTRIBUTE}'"),
+                        ));
+                    }
+                    for (krate, line) in source.extern_crates() {
+                        if krate == "std" {
+                            violations
+                                .push(source.violation(line, "'extern crate std' is not allowed"));
                         }
-                    } else {
-                        // TODO: ForbidStd shouldn't check whether file is empty
-                        bail!(format!("file {file:?} is empty"))
                     }
-                }
-                Ok(())
-            }
-            Command::CargoCompileTestMiniFrunk => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-generic")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-labelled")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-transmogrify")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
+                    for usage in source.path_usages() {
+                        if !usage.prelude
+                            && usage.segments.first().map(String::as_str) == Some("std")
+                        {
+                            let message =
+                                format!("use of '{}' is not allowed", usage.segments.join("::"));
+                            violations.push(source.violation(usage.line, message));
+                        }
+                    }
+                    violations
+                });
             }
-            Command::CargoCompileTestOrm => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetimes-create")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetimes-get")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
+            Command::ForbidApi => {
+                let rules = context.get_forbid_rules();
+                for violation in check_manifest(rules, context.get_workdir())? {
+                    result.stderr += &format!("{violation}\n");
                 }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
+                result.stderr += &check_sources(context, |source| check_source(rules, source));
             }
-            Command::CargoCompileTestSnapshot => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetime")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
+            Command::Custom(custom) => {
+                result.command =
+                    format!("{} {}", self.get_shell_line()?, command.get_shell_line()?)
+                        .trim()
+                        .to_string();
+                let workdir = match custom.cwd() {
+                    Some(cwd) => context.get_workdir().join(cwd),
+                    None => context.get_workdir().to_path_buf(),
+                };
+                let shell_line: Vec<_> = std::iter::once(custom.program())
+                    .chain(custom.args().iter().map(String::as_str))
+                    .collect();
+                let execution =
+                    self.launch(&shell_line, context, &workdir, custom.env(), timeout)?;
+                result.exit_code = execution.status.code();
+                result.stdout = execution.stdout;
+                result.stderr = execution.stderr;
+                if execution.canceled {
+                    result.outcome = Outcome::Skipped;
+                } else if execution.timed_out {
+                    result.outcome = Outcome::TimedOut;
+                } else if execution.status.success() != (custom.expect() == Expectation::Success) {
+                    if custom.expect() == Expectation::Failure {
+                        result.stderr += "command was expected to fail, but it succeeded\n";
+                    }
+                    result.outcome = Outcome::Failed;
+                } else if custom.expect() == Expectation::Failure && !context.get_options().quiet {
+                    println!(
+                        "Command \"{}\" failed as expected, don't worry :)",
+                        command.name()
+                    );
                 }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
             }
             Command::CargoFmt
             | Command::CargoClippy
@@ -209,8 +223,91 @@ impl Toolchain {
             | Command::CargoTestDebug
             | Command::PythonTest
             | Command::CargoMiriTest => {
-                launch!(self, command, context)
+                result.command =
+                    format!("{} {}", self.get_shell_line()?, command.get_shell_line()?)
+                        .trim()
+                        .to_string();
+                let shell_line = command.get_shell_line()?;
+                let shell_line: Vec<_> = shell_line.split(' ').collect();
+                let execution = self.launch(
+                    &shell_line,
+                    context,
+                    context.get_workdir(),
+                    &BTreeMap::new(),
+                    timeout,
+                )?;
+                result.exit_code = execution.status.code();
+                result.stdout = execution.stdout;
+                result.stderr = execution.stderr;
+                if execution.canceled {
+                    result.outcome = Outcome::Skipped;
+                } else if execution.timed_out {
+                    result.outcome = Outcome::TimedOut;
+                } else if !execution.status.success() {
+                    result.outcome = Outcome::Failed;
+                }
+            }
+        }
+        if matches!(
+            command,
+            Command::ForbidUnsafe
+                | Command::ForbidCollections
+                | Command::ForbidStd
+                | Command::ForbidApi
+        ) && !result.stderr.is_empty()
+        {
+            if !context.get_options().quiet {
+                eprint!("{}", result.stderr);
+            }
+            result.outcome = Outcome::Failed;
+        }
+        result.duration = start.elapsed();
+        // Failing tests are the point of the commands expected to fail
+        if !matches!(command, Command::Custom(custom) if custom.expect() == Expectation::Failure) {
+            result.tests = parse_test_output(&result.stdout);
+        }
+        if result.outcome == Outcome::TimedOut {
+            let message = format!(
+                "command timed out after {:.1}s\n",
+                result.duration.as_secs_f64()
+            );
+            if !context.get_options().quiet {
+                eprint!("{message}");
+            }
+            result.stderr += &message;
+        }
+        Ok(result)
+    }
+}
+
+fn is_collection_usage(usage: &PathUsage) -> bool {
+    let glob = usage.segments.last().map(String::as_str) == Some("*");
+    let forbidden_name = usage
+        .segments
+        .iter()
+        .any(|segment| FORBID_COLLECTIONS_PATTERNS.contains(&segment.as_str()));
+    (usage.starts_with("std::collections") && (glob || forbidden_name))
+        || (usage.starts_with("std::vec") && (glob || forbidden_name || usage.macro_call))
+}
+
+/// Parses every user file and collects the violations found by `check` into diagnostic lines.
+fn check_sources(
+    context: &CommandContext,
+    check: impl Fn(&SourceFile) -> Vec<Violation>,
+) -> String {
+    let mut diagnostics = String::new();
+    for file in context.get_user_files() {
+        if file.extension() != Some("rs".as_ref()) {
+            continue;
+        }
+        match SourceFile::parse(file, context.get_workdir()) {
+            Ok(source) => {
+                for violation in check(&source) {
+                    diagnostics += &format!("{violation}\n");
+                }
             }
+            Err(err) => diagnostics += &format!("{err:#}\n"),
         }
     }
+    diagnostics
 }
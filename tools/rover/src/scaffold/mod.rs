@@ -0,0 +1,2 @@
+pub mod run_new;
+mod templates;
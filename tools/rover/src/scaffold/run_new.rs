@@ -0,0 +1,453 @@
+use super::templates::*;
+use crate::repository::{
+    problem::DEFAULT_YML_NAME,
+    repo::{Repository, COMPOSE_CONFIG},
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+use toml_edit::{Array, Document, Value};
+
+const WORKSPACE_MANIFEST: &str = "Cargo.toml";
+
+#[derive(Clone, Copy)]
+pub enum Template {
+    Lib,
+    Bin,
+    ProcMacro,
+}
+
+impl Template {
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "lib" => Self::Lib,
+            "bin" => Self::Bin,
+            "proc-macro" => Self::ProcMacro,
+            name => bail!("template \"{name}\" is not supported"),
+        })
+    }
+
+    /// Files of the problem relative to its directory with their contents.
+    fn files(&self, krate: &str) -> Vec<(PathBuf, &'static str)> {
+        let mut files = vec![(PathBuf::from("README.md"), README)];
+        match self {
+            Self::Lib => files.extend([
+                (PathBuf::from("Cargo.toml"), LIB_CARGO_TOML),
+                (PathBuf::from(DEFAULT_YML_NAME), LIB_CONFIG),
+                (PathBuf::from("src/lib.rs"), LIB_RS),
+                (PathBuf::from("tests/tests.rs"), LIB_TESTS),
+            ]),
+            Self::Bin => files.extend([
+                (PathBuf::from("Cargo.toml"), BIN_CARGO_TOML),
+                (PathBuf::from(DEFAULT_YML_NAME), BIN_CONFIG),
+                (PathBuf::from("src/main.rs"), MAIN_RS),
+                (PathBuf::from("tests/tests.rs"), BIN_TESTS),
+            ]),
+            Self::ProcMacro => {
+                let derive = PathBuf::from(format!("{krate}-derive"));
+                files.extend([
+                    (PathBuf::from("Cargo.toml"), PROC_MACRO_CARGO_TOML),
+                    (PathBuf::from(DEFAULT_YML_NAME), PROC_MACRO_CONFIG),
+                    (PathBuf::from("src/lib.rs"), PROC_MACRO_LIB_RS),
+                    (PathBuf::from("tests/tests.rs"), PROC_MACRO_TESTS),
+                    (derive.join("Cargo.toml"), DERIVE_CARGO_TOML),
+                    (derive.join("src/lib.rs"), DERIVE_LIB_RS),
+                ])
+            }
+        }
+        files
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn authors_line() -> String {
+    let config = match git2::Config::open_default() {
+        Ok(config) => config,
+        Err(_) => return String::new(),
+    };
+    match config.get_string("user.name") {
+        Ok(name) => format_authors(&name, config.get_string("user.email").ok().as_deref()),
+        Err(_) => String::new(),
+    }
+}
+
+/// The `authors` line of `Cargo.toml`, the name is quoted as a TOML string.
+fn format_authors(name: &str, email: Option<&str>) -> String {
+    let author = match email {
+        Some(email) => format!("{name} <{email}>"),
+        None => name.to_string(),
+    };
+    format!("authors = [{}]\n", Value::from(author))
+}
+
+fn title_of(krate: &str) -> String {
+    krate
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Adds the problem to the members of the workspace manifest, after the other problems.
+fn register_in_workspace(
+    path: &Path,
+    content: &str,
+    problems_folder: &Path,
+    member: &str,
+) -> Result<String> {
+    let mut manifest: Document = content
+        .parse()
+        .with_context(|| format!("failed to parse {path:?}"))?;
+    let members = manifest["workspace"]["members"]
+        .as_array_mut()
+        .with_context(|| format!("{path:?} has no workspace members list"))?;
+    if members.iter().any(|value| value.as_str() == Some(member)) {
+        bail!("{member} is already a member of the workspace")
+    }
+    let prefix = format!("{}/", problems_folder.display());
+    let position = members
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| {
+            value
+                .as_str()
+                .is_some_and(|value| value.starts_with(&prefix))
+        })
+        .last()
+        .map_or(members.len(), |(position, _)| position + 1);
+    insert_member(members, position, member);
+    Ok(manifest.to_string())
+}
+
+fn insert_member(members: &mut Array, position: usize, member: &str) {
+    let prefix = members
+        .get(position.saturating_sub(1))
+        .and_then(|value| value.decor().prefix())
+        .and_then(|prefix| prefix.as_str())
+        .map(|prefix| {
+            // Keep the indentation, but not the comments and blank lines
+            let indent = prefix.rsplit('\n').next().unwrap_or_default();
+            format!("\n{indent}")
+        })
+        .unwrap_or_else(|| "\n    ".to_string());
+    members.insert(position, member);
+    if let Some(value) = members.get_mut(position) {
+        value.decor_mut().set_prefix(prefix);
+    }
+}
+
+/// Adds the problem to the `problems` list of the compose config, keeping its formatting.
+fn register_in_compose(path: &Path, content: &str, problem: &str) -> Result<String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.trim_end() == "problems:")
+        .with_context(|| format!("{path:?} has no \"problems:\" block list"))?;
+    let items = lines[start + 1..]
+        .iter()
+        .take_while(|line| line.trim_start().starts_with("- ") || line.trim().is_empty())
+        .count();
+    let last_item = lines[start + 1..start + 1 + items]
+        .iter()
+        .rposition(|line| line.trim_start().starts_with("- "));
+    let (position, indent) = match last_item {
+        Some(last) => {
+            let line = lines[start + 1 + last];
+            (
+                start + 2 + last,
+                &line[..line.len() - line.trim_start().len()],
+            )
+        }
+        None => (start + 1, "  "),
+    };
+    if lines[start + 1..start + 1 + items]
+        .iter()
+        .any(|line| line.trim_start().trim_start_matches("- ").trim() == problem)
+    {
+        bail!("{problem} is already in the problems of {path:?}")
+    }
+    let item = format!("{indent}- {problem}");
+    lines.insert(position, &item);
+    Ok(lines.join("\n") + "\n")
+}
+
+/// A file of the course repository changed by the command with its original content.
+struct Edit {
+    path: PathBuf,
+    original: String,
+    updated: String,
+}
+
+impl Edit {
+    fn new(path: PathBuf, update: impl FnOnce(&Path, &str) -> Result<String>) -> Result<Self> {
+        let original =
+            fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+        let updated = update(&path, &original)?;
+        Ok(Self {
+            path,
+            original,
+            updated,
+        })
+    }
+}
+
+fn validate(repository: &Repository, problem_path: &Path, member: &str) -> Result<()> {
+    let problem = repository.problem_from_path(problem_path)?;
+    let config = problem.config()?;
+    if config.get_relative_user_files().is_empty() {
+        bail!("allowed-patterns of {member} match no files")
+    }
+    let output = process::Command::new("cargo")
+        .args([
+            "metadata",
+            "--no-deps",
+            "--format-version",
+            "1",
+            "--offline",
+        ])
+        .current_dir(repository.get_path())
+        .output()
+        .context("failed to launch cargo metadata")?;
+    if !output.status.success() {
+        bail!(
+            "workspace is broken after adding {member}:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("failed to parse cargo metadata")?;
+    let manifest = problem_path.join(WORKSPACE_MANIFEST);
+    let is_member = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|package| {
+            package["manifest_path"]
+                .as_str()
+                .is_some_and(|path| Path::new(path) == manifest)
+        });
+    if !is_member {
+        bail!("cargo doesn't see {member} as a workspace member")
+    }
+    Ok(())
+}
+
+pub fn run_new(path: &Path, name: &str, template: Template) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let (group, krate) = name
+        .split_once('/')
+        .with_context(|| format!("problem name \"{name}\" is not in \"GROUP/TITLE\" form"))?;
+    if !is_valid_name(group) || !is_valid_name(krate) {
+        bail!("group and title must consist of lowercase letters, digits and '-' only")
+    }
+    for problem in repository.problems()? {
+        if problem.title() == krate {
+            bail!(
+                "crate name {krate} is already used by {}",
+                problem.branch_name()
+            )
+        }
+    }
+    let member = format!("{}/{group}/{krate}", repository.problems_folder().display());
+    let problem_path = repository.get_path().join(&member);
+    if problem_path.exists() {
+        bail!("{problem_path:?} already exists")
+    }
+    // Everything which may fail is checked before the repository is changed
+    let root = repository.get_path();
+    let mut edits = vec![Edit::new(
+        root.join(WORKSPACE_MANIFEST),
+        |path, content| register_in_workspace(path, content, repository.problems_folder(), &member),
+    )?];
+    let compose_path = root.join(COMPOSE_CONFIG);
+    let has_compose = compose_path.is_file();
+    if has_compose {
+        edits.push(Edit::new(compose_path, |path, content| {
+            register_in_compose(path, content, name)
+        })?);
+    }
+    let group_path = problem_path
+        .parent()
+        .context("problem path has no parent")?;
+    let created_group = !group_path.exists();
+    let result = create_problem(&repository, &problem_path, &member, template, &edits);
+    if let Err(err) = result {
+        for edit in &edits {
+            let _ = fs::write(&edit.path, &edit.original);
+        }
+        let _ = fs::remove_dir_all(if created_group {
+            group_path
+        } else {
+            &problem_path
+        });
+        return Err(err.context(format!(
+            "failed to create {name}, the changes were rolled back"
+        )));
+    }
+    if !has_compose {
+        println!("no {COMPOSE_CONFIG} in the course repository, skipping it");
+    }
+    println!("\nProblem {group}/{krate} is ready");
+    Ok(())
+}
+
+fn create_problem(
+    repository: &Repository,
+    problem_path: &Path,
+    member: &str,
+    template: Template,
+    edits: &[Edit],
+) -> Result<()> {
+    let krate = problem_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("problem path has no name")?;
+    let authors = authors_line();
+    let ident = krate.replace('-', "_");
+    let title = title_of(krate);
+    for (file, template) in template.files(krate) {
+        let content = template
+            .replace("{crate}", krate)
+            .replace("{ident}", &ident)
+            .replace("{title}", &title)
+            .replace("{authors}", &authors);
+        let file = problem_path.join(file);
+        fs::create_dir_all(file.parent().unwrap())
+            .with_context(|| format!("failed to create directory for {file:?}"))?;
+        fs::write(&file, content).with_context(|| format!("failed to write {file:?}"))?;
+        println!("created {}", file.display());
+    }
+    for edit in edits {
+        fs::write(&edit.path, &edit.updated)
+            .with_context(|| format!("failed to write {:?}", edit.path))?;
+        println!("updated {}", edit.path.display());
+    }
+    validate(repository, problem_path, member)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const WORKSPACE: &str = "[workspace]\nmembers = [\n    \"problems/intro/hello\",\n]\n";
+    const COMPOSE: &str = "problems:\n  - intro/hello\n";
+
+    fn course() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let hello = root.join("problems/intro/hello");
+        fs::create_dir_all(hello.join("src")).unwrap();
+        fs::write(
+            hello.join("Cargo.toml"),
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(hello.join("src/lib.rs"), "").unwrap();
+        fs::write(
+            hello.join(DEFAULT_YML_NAME),
+            "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
+        )
+        .unwrap();
+        fs::write(root.join(".rover.toml"), "").unwrap();
+        fs::write(root.join(WORKSPACE_MANIFEST), WORKSPACE).unwrap();
+        fs::write(root.join(COMPOSE_CONFIG), COMPOSE).unwrap();
+        dir
+    }
+
+    fn read(root: &Path, path: &str) -> String {
+        fs::read_to_string(root.join(path)).unwrap()
+    }
+
+    #[test]
+    fn creates_every_template() {
+        let dir = course();
+        let root = dir.path();
+        run_new(root, "intro/add", Template::Lib).unwrap();
+        run_new(root, "intro/echo-line", Template::Bin).unwrap();
+        run_new(root, "macros/describe", Template::ProcMacro).unwrap();
+        assert_eq!(
+            read(root, WORKSPACE_MANIFEST),
+            "[workspace]\nmembers = [\n    \"problems/intro/hello\",\n    \"problems/intro/add\",\n    \
+             \"problems/intro/echo-line\",\n    \"problems/macros/describe\",\n]\n"
+        );
+        assert_eq!(
+            read(root, COMPOSE_CONFIG),
+            "problems:\n  - intro/hello\n  - intro/add\n  - intro/echo-line\n  - macros/describe\n"
+        );
+        assert!(read(root, "problems/intro/add/src/lib.rs").contains("pub fn answer()"));
+        let manifest: toml::Table = read(root, "problems/intro/echo-line/Cargo.toml")
+            .parse()
+            .unwrap();
+        assert_eq!(manifest["bin"][0]["name"].as_str(), Some("echo-line"));
+        assert!(read(root, "problems/intro/echo-line/tests/tests.rs")
+            .contains("CARGO_BIN_EXE_echo-line"));
+        assert!(read(root, "problems/macros/describe/src/lib.rs")
+            .contains("pub use describe_derive::Describe;"));
+        assert!(root
+            .join("problems/macros/describe/describe-derive/src/lib.rs")
+            .is_file());
+    }
+
+    #[test]
+    fn refuses_name_collisions() {
+        let dir = course();
+        let root = dir.path();
+        let err = run_new(root, "other/hello", Template::Lib).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "crate name hello is already used by intro/hello"
+        );
+        assert!(run_new(root, "Intro/add", Template::Lib).is_err());
+        assert!(run_new(root, "add", Template::Lib).is_err());
+        fs::write(root.join(COMPOSE_CONFIG), "problems:\n  - intro/add\n").unwrap();
+        let err = run_new(root, "intro/add", Template::Lib).unwrap_err();
+        assert!(err.to_string().contains("already in the problems"), "{err}");
+        assert!(!root.join("problems/intro/add").exists());
+        assert_eq!(read(root, WORKSPACE_MANIFEST), WORKSPACE);
+    }
+
+    #[test]
+    fn rolls_back_on_failure() {
+        let dir = course();
+        let root = dir.path();
+        // The workspace is broken, so it doesn't validate after adding the problem
+        fs::write(root.join("problems/intro/hello/Cargo.toml"), "[package]\n").unwrap();
+        let err = run_new(root, "strings/split", Template::Lib).unwrap_err();
+        assert!(format!("{err:#}").contains("rolled back"), "{err:#}");
+        assert!(!root.join("problems/strings").exists());
+        assert_eq!(read(root, WORKSPACE_MANIFEST), WORKSPACE);
+        assert_eq!(read(root, COMPOSE_CONFIG), COMPOSE);
+
+        assert!(run_new(root, "intro/split", Template::Lib).is_err());
+        assert!(root.join("problems/intro/hello").is_dir());
+        assert!(!root.join("problems/intro/split").exists());
+    }
+
+    #[test]
+    fn authors_are_quoted() {
+        let line = format_authors("Jo \"JJ\" O'Neil \\", Some("jo@example.com"));
+        let manifest: toml::Table = line.parse().unwrap();
+        assert_eq!(
+            manifest["authors"][0].as_str(),
+            Some("Jo \"JJ\" O'Neil \\ <jo@example.com>")
+        );
+        assert_eq!(format_authors("Jo", None), "authors = [\"Jo\"]\n");
+    }
+}
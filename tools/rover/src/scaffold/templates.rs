@@ -0,0 +1,166 @@
+//! Files of the new problems. `{crate}` is replaced with the crate name, `{ident}` with
+//! the crate name as a Rust identifier, `{title}` with the README title and `{authors}`
+//! with the authors line of `Cargo.toml`.
+
+pub const README: &str = "# {title}
+
+## Task
+
+TODO: describe the task.
+";
+
+pub const LIB_CARGO_TOML: &str = "[package]
+name = \"{crate}\"
+version = \"0.1.0\"
+{authors}edition = \"2021\"
+";
+
+pub const BIN_CARGO_TOML: &str = "[package]
+name = \"{crate}\"
+version = \"0.1.0\"
+{authors}edition = \"2021\"
+
+[[bin]]
+name = \"{crate}\"
+path = \"src/main.rs\"
+";
+
+pub const LIB_CONFIG: &str = "toolchain: stable
+allowed-patterns:
+  - src/lib.rs
+steps:
+  linters:
+    - forbid-unsafe
+    - cargo-fmt
+    - cargo-clippy
+  testing:
+    - cargo-test
+";
+
+pub const LIB_RS: &str = "#![forbid(unsafe_code)]
+
+pub fn answer() -> i32 {
+    // compose::begin_private(unimplemented)
+    42
+    // compose::end_private
+}
+";
+
+pub const LIB_TESTS: &str = "use {ident}::answer;
+
+#[test]
+fn it_works() {
+    assert_eq!(answer(), 42);
+}
+";
+
+pub const BIN_CONFIG: &str = "toolchain: stable
+allowed-patterns:
+  - src/main.rs
+steps:
+  linters:
+    - forbid-unsafe
+    - cargo-fmt
+    - cargo-clippy
+  testing:
+    - cargo-test
+";
+
+pub const MAIN_RS: &str = "#![forbid(unsafe_code)]
+
+fn main() {
+    // compose::begin_private(unimplemented)
+    println!(\"42\");
+    // compose::end_private
+}
+";
+
+pub const BIN_TESTS: &str = "use std::process::Command;
+
+const BINARY_PATH: &str = env!(\"CARGO_BIN_EXE_{crate}\");
+
+#[test]
+fn it_works() {
+    let output = Command::new(BINARY_PATH).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), \"42\\n\");
+}
+";
+
+pub const PROC_MACRO_CARGO_TOML: &str = "[package]
+name = \"{crate}\"
+version = \"0.1.0\"
+{authors}edition = \"2021\"
+
+[dependencies]
+{crate}-derive = { path = \"./{crate}-derive\" }
+";
+
+pub const PROC_MACRO_CONFIG: &str = "toolchain: stable
+allowed-patterns:
+  - src/lib.rs
+  - {crate}-derive/src/lib.rs
+steps:
+  linters:
+    - forbid-unsafe
+    - cargo-fmt
+    - cargo-clippy
+  testing:
+    - cargo-test
+";
+
+pub const PROC_MACRO_LIB_RS: &str = "#![forbid(unsafe_code)]
+
+pub use {ident}_derive::Describe;
+
+pub trait Describe {
+    fn describe() -> &'static str;
+}
+";
+
+pub const DERIVE_CARGO_TOML: &str = "[package]
+name = \"{crate}-derive\"
+version = \"0.1.0\"
+{authors}edition = \"2021\"
+
+[lib]
+proc-macro = true
+
+[dependencies]
+quote = \">= 1.0.17\"
+syn = { version = \">= 1.0.90\", features = [\"full\"] }
+";
+
+pub const DERIVE_LIB_RS: &str = "#![forbid(unsafe_code)]
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput};
+
+#[proc_macro_derive(Describe)]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    // compose::begin_private(unimplemented)
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let description = name.to_string();
+    quote! {
+        impl ::{ident}::Describe for #name {
+            fn describe() -> &'static str {
+                #description
+            }
+        }
+    }
+    .into()
+    // compose::end_private
+}
+";
+
+pub const PROC_MACRO_TESTS: &str = "use {ident}::Describe;
+
+#[derive(Describe)]
+struct Point;
+
+#[test]
+fn it_works() {
+    assert_eq!(Point::describe(), \"Point\");
+}
+";
@@ -0,0 +1,3 @@
+pub mod run_similarity;
+mod tokens;
+mod winnow;
@@ -0,0 +1,174 @@
+use super::winnow::{compare, Comparison, Document};
+use crate::{
+    git::git_repo::GitRepo,
+    repository::{problem::Problem, repo::Repository},
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct SimilarityOptions {
+    /// Pairs less similar than this share of fingerprints are not reported.
+    pub min_similarity: f64,
+    /// Compare only the solutions of the problem, given as `GROUP/TITLE`.
+    pub problem: Option<String>,
+}
+
+struct Pair {
+    problem: String,
+    left: String,
+    right: String,
+    files: (Vec<PathBuf>, Vec<PathBuf>),
+    comparison: Comparison,
+}
+
+/// The solution repositories: the directories of `solutions`, named after the students.
+fn students(solutions: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut students = Vec::new();
+    for entry in fs::read_dir(solutions).with_context(|| format!("failed to read {solutions:?}"))? {
+        let path = entry.context("failed to read solutions entry")?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if path.is_dir() && !name.starts_with('.') {
+            students.push((name, path));
+        }
+    }
+    students.sort();
+    Ok(students)
+}
+
+/// Reads the files of the problem from its branch, or from the working tree if the
+/// solutions are not a git repository.
+fn read_solution(
+    solutions_repo: &Path,
+    problem: &Problem,
+    files: &[PathBuf],
+) -> Result<Vec<(PathBuf, String)>> {
+    let relative_path = problem.relative_path();
+    let mut solution = Vec::new();
+    match GitRepo::open(solutions_repo) {
+        Ok(git) => {
+            let branch_name = problem.branch_name();
+            if !git.has_branch(&branch_name) {
+                return Ok(solution);
+            }
+            let revision = git.branch_base(&branch_name)?;
+            for file in files {
+                if let Some(content) = git.read_file(&revision, &relative_path.join(file))? {
+                    solution.push((file.clone(), String::from_utf8_lossy(&content).into_owned()));
+                }
+            }
+        }
+        Err(_) => {
+            for file in files {
+                if let Ok(content) = fs::read(solutions_repo.join(&relative_path).join(file)) {
+                    solution.push((file.clone(), String::from_utf8_lossy(&content).into_owned()));
+                }
+            }
+        }
+    }
+    Ok(solution)
+}
+
+fn compare_problem(
+    problem: &Problem,
+    students: &[(String, PathBuf)],
+    pairs: &mut Vec<Pair>,
+) -> Result<()> {
+    let config = problem.config()?;
+    let files = config.get_relative_user_files();
+    let template = files
+        .iter()
+        .filter_map(|file| {
+            let content = fs::read_to_string(problem.get_path().join(file)).ok()?;
+            Some((file.clone(), content))
+        })
+        .collect();
+    let ignored = Document::new(template).hashes();
+    let mut documents = Vec::new();
+    for (student, path) in students {
+        let solution = read_solution(path, problem, files)
+            .with_context(|| format!("failed to read solution of {student}"))?;
+        if !solution.is_empty() {
+            documents.push((student, Document::new(solution)));
+        }
+    }
+    for (i, (left, left_document)) in documents.iter().enumerate() {
+        for (right, right_document) in &documents[i + 1..] {
+            pairs.push(Pair {
+                problem: problem.branch_name(),
+                left: left.to_string(),
+                right: right.to_string(),
+                files: (left_document.files.clone(), right_document.files.clone()),
+                comparison: compare(left_document, right_document, &ignored),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn print_report(pairs: &[Pair]) {
+    for pair in pairs {
+        let comparison = &pair.comparison;
+        println!(
+            "{:5.1}%  {}  {} ({:.1}%) ~ {} ({:.1}%)",
+            comparison.similarity() * 100.0,
+            pair.problem,
+            pair.left,
+            comparison.left_share * 100.0,
+            pair.right,
+            comparison.right_share * 100.0
+        );
+        for region in &comparison.regions {
+            let (file, start, end) = region.left;
+            let (other_file, other_start, other_end) = region.right;
+            println!(
+                "    {}:{start}-{end} ~ {}:{other_start}-{other_end}",
+                pair.files.0[file].display(),
+                pair.files.1[other_file].display()
+            );
+        }
+    }
+}
+
+pub fn run_similarity(path: &Path, solutions: &Path, options: &SimilarityOptions) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let students = students(solutions)?;
+    if students.len() < 2 {
+        bail!("need at least two solution repositories in {solutions:?} to compare")
+    }
+    let problems: Vec<_> = repository
+        .problems()?
+        .into_iter()
+        .filter(|problem| {
+            options
+                .problem
+                .as_ref()
+                .is_none_or(|name| *name == format!("{}/{}", problem.group(), problem.title()))
+        })
+        .collect();
+    if let Some(name) = options.problem.as_ref().filter(|_| problems.is_empty()) {
+        bail!("there's no problem {name} in the course repository")
+    }
+    let mut pairs = Vec::new();
+    for problem in &problems {
+        compare_problem(problem, &students, &mut pairs)
+            .with_context(|| format!("failed to compare solutions of {}", problem.branch_name()))?;
+    }
+    let total = pairs.len();
+    pairs.retain(|pair| pair.comparison.similarity() >= options.min_similarity);
+    pairs.sort_by(|a, b| {
+        b.comparison
+            .similarity()
+            .total_cmp(&a.comparison.similarity())
+            .then_with(|| a.problem.cmp(&b.problem))
+    });
+    println!(
+        "{} of {total} pairs of solutions are at least {:.0}% similar",
+        pairs.len(),
+        options.min_similarity * 100.0
+    );
+    print_report(&pairs);
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use std::path::Path;
+
+/// A normalized token and the 1-based line it starts at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub line: usize,
+}
+
+const IDENTIFIER: &str = "$id";
+const LITERAL: &str = "$lit";
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+fn token(text: &str, line: usize) -> Token {
+    Token {
+        text: text.to_string(),
+        line,
+    }
+}
+
+/// Tokenizes the file, replacing identifiers and literals with placeholders and dropping
+/// whitespace and comments, so that renaming and reformatting don't hide copying.
+pub fn normalize(path: &Path, content: &str) -> Vec<Token> {
+    if path.extension().is_some_and(|extension| extension == "rs") {
+        if let Ok(stream) = content.parse::<TokenStream>() {
+            let mut tokens = Vec::new();
+            normalize_rust(stream, &mut tokens);
+            return tokens;
+        }
+    }
+    normalize_text(content)
+}
+
+fn normalize_rust(stream: TokenStream, tokens: &mut Vec<Token>) {
+    let mut trees = stream.into_iter().peekable();
+    while let Some(tree) = trees.next() {
+        let line = tree.span().start().line;
+        match tree {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    tokens.push(token(open, line));
+                }
+                normalize_rust(group.stream(), tokens);
+                if !close.is_empty() {
+                    tokens.push(token(close, group.span_close().start().line));
+                }
+            }
+            TokenTree::Punct(punct) => {
+                // Doc comments are lexed as `#[doc = "..."]` attributes
+                let is_doc = punct.as_char() == '#'
+                    && matches!(trees.peek(), Some(TokenTree::Group(group))
+                        if group.delimiter() == Delimiter::Bracket
+                            && matches!(group.stream().into_iter().next(),
+                                Some(TokenTree::Ident(ident)) if ident == "doc"));
+                if is_doc {
+                    trees.next();
+                } else {
+                    tokens.push(token(&punct.as_char().to_string(), line));
+                }
+            }
+            TokenTree::Ident(ident) => {
+                let text = ident.to_string();
+                if RUST_KEYWORDS.contains(&text.as_str()) {
+                    tokens.push(token(&text, line));
+                } else {
+                    tokens.push(token(IDENTIFIER, line));
+                }
+            }
+            TokenTree::Literal(_) => tokens.push(token(LITERAL, line)),
+        }
+    }
+}
+
+/// A language-agnostic fallback: words, numbers, quoted strings and single symbols,
+/// with `//` and `#` line comments dropped.
+fn normalize_text(content: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            if c == '#' || line[start..].starts_with("//") {
+                break;
+            }
+            if c.is_alphanumeric() || c == '_' {
+                while chars
+                    .peek()
+                    .is_some_and(|&(_, c)| c.is_alphanumeric() || c == '_')
+                {
+                    chars.next();
+                }
+                let placeholder = if c.is_numeric() { LITERAL } else { IDENTIFIER };
+                tokens.push(token(placeholder, line_number));
+            } else if c == '"' || c == '\'' {
+                let mut escaped = false;
+                for (_, next) in chars.by_ref() {
+                    if next == c && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+                tokens.push(token(LITERAL, line_number));
+            } else {
+                tokens.push(token(&c.to_string(), line_number));
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(path: &str, content: &str) -> Vec<String> {
+        normalize(Path::new(path), content)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn renaming_and_formatting_are_ignored() {
+        let original = "/// Adds.\nfn add(a: i32, b: i32) -> i32 {\n    a + b // sum\n}\n";
+        let renamed = "fn plus(x: i32,\n        y: i32) -> i32 { x + y }";
+        assert_eq!(texts("a.rs", original), texts("b.rs", renamed));
+        assert_eq!(
+            texts("a.rs", "let x = \"s\";")[..4],
+            ["let", IDENTIFIER, "=", LITERAL]
+        );
+
+        let tokens = normalize(Path::new("a.rs"), original);
+        assert_eq!(tokens.first().unwrap().line, 2);
+        assert_eq!(tokens.last().unwrap().line, 4);
+    }
+
+    #[test]
+    fn other_languages() {
+        assert_eq!(
+            texts("a.py", "def f(x):  # comment\n    return x + 'a#b'\n"),
+            [
+                IDENTIFIER, IDENTIFIER, "(", IDENTIFIER, ")", ":", IDENTIFIER, IDENTIFIER, "+",
+                LITERAL
+            ]
+        );
+    }
+}
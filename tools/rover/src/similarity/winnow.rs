@@ -0,0 +1,231 @@
+use super::tokens::{normalize, Token};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
+/// Number of tokens in a hashed k-gram, shorter matches are considered a coincidence.
+const K: usize = 12;
+/// Number of consecutive k-grams a fingerprint is selected from, any match of
+/// `K + WINDOW - 1` tokens is guaranteed to be found.
+const WINDOW: usize = 8;
+
+/// A selected k-gram hash with the lines of the file it covers.
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    pub hash: u64,
+    pub file: usize,
+    pub lines: (usize, usize),
+}
+
+/// The fingerprints of all the files of a solution.
+pub struct Document {
+    pub files: Vec<PathBuf>,
+    pub fingerprints: Vec<Fingerprint>,
+}
+
+/// A region of one solution similar to a region of another one, as file indices and lines.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Region {
+    pub left: (usize, usize, usize),
+    pub right: (usize, usize, usize),
+}
+
+pub struct Comparison {
+    /// Shares of the fingerprints of each solution found in the other one.
+    pub left_share: f64,
+    pub right_share: f64,
+    pub regions: Vec<Region>,
+}
+
+impl Comparison {
+    /// How much of the smaller solution is contained in the other one.
+    pub fn similarity(&self) -> f64 {
+        self.left_share.max(self.right_share)
+    }
+}
+
+fn kgram_hashes(tokens: &[Token]) -> Vec<(u64, (usize, usize))> {
+    tokens
+        .windows(K)
+        .map(|kgram| {
+            let mut hasher = DefaultHasher::new();
+            for token in kgram {
+                token.text.hash(&mut hasher);
+            }
+            (hasher.finish(), (kgram[0].line, kgram[K - 1].line))
+        })
+        .collect()
+}
+
+/// Selects the minimal hash of every window, the rightmost one on ties.
+fn winnow(hashes: &[(u64, (usize, usize))]) -> Vec<usize> {
+    let mut selected: Vec<usize> = Vec::new();
+    for start in 0..hashes.len().saturating_sub(WINDOW - 1).max(1) {
+        let window = &hashes[start..hashes.len().min(start + WINDOW)];
+        let min = window
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, (hash, _))| *hash)
+            .map(|(i, _)| start + i);
+        if let Some(min) = min {
+            if selected.last() != Some(&min) {
+                selected.push(min);
+            }
+        }
+    }
+    selected
+}
+
+impl Document {
+    pub fn new(files: Vec<(PathBuf, String)>) -> Self {
+        let mut document = Self {
+            files: Vec::new(),
+            fingerprints: Vec::new(),
+        };
+        for (file, (path, content)) in files.into_iter().enumerate() {
+            let hashes = kgram_hashes(&normalize(&path, &content));
+            for i in winnow(&hashes) {
+                let (hash, lines) = hashes[i];
+                document
+                    .fingerprints
+                    .push(Fingerprint { hash, file, lines });
+            }
+            document.files.push(path);
+        }
+        document
+    }
+
+    pub fn hashes(&self) -> HashSet<u64> {
+        self.fingerprints.iter().map(|print| print.hash).collect()
+    }
+}
+
+fn overlaps(range: (usize, usize), lines: (usize, usize)) -> bool {
+    lines.0 <= range.1 + 1 && range.0 <= lines.1 + 1
+}
+
+/// Compares two solutions, the `ignored` fingerprints (e.g. of the template) don't count.
+pub fn compare(left: &Document, right: &Document, ignored: &HashSet<u64>) -> Comparison {
+    let left_hashes: BTreeSet<_> = left.hashes().difference(ignored).copied().collect();
+    let right_hashes: BTreeSet<_> = right.hashes().difference(ignored).copied().collect();
+    let shared = left_hashes.intersection(&right_hashes).count();
+    let share = |total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            shared as f64 / total as f64
+        }
+    };
+
+    let mut in_right: HashMap<u64, Vec<&Fingerprint>> = HashMap::new();
+    for print in &right.fingerprints {
+        in_right.entry(print.hash).or_default().push(print);
+    }
+    let mut regions: Vec<Region> = Vec::new();
+    for print in &left.fingerprints {
+        if ignored.contains(&print.hash) {
+            continue;
+        }
+        let occurrences = match in_right.get(&print.hash) {
+            Some(occurrences) => occurrences,
+            None => continue,
+        };
+        // Repeated code matches in many places, the one continuing the last region is preferred
+        let continued = regions.last_mut().and_then(|last| {
+            let other = occurrences.iter().find(|other| {
+                last.left.0 == print.file
+                    && last.right.0 == other.file
+                    && overlaps((last.left.1, last.left.2), print.lines)
+                    && overlaps((last.right.1, last.right.2), other.lines)
+            })?;
+            Some((last, other))
+        });
+        if let Some((last, other)) = continued {
+            last.left.1 = last.left.1.min(print.lines.0);
+            last.left.2 = last.left.2.max(print.lines.1);
+            last.right.1 = last.right.1.min(other.lines.0);
+            last.right.2 = last.right.2.max(other.lines.1);
+            continue;
+        }
+        let other = occurrences[0];
+        regions.push(Region {
+            left: (print.file, print.lines.0, print.lines.1),
+            right: (other.file, other.lines.0, other.lines.1),
+        });
+    }
+    Comparison {
+        left_share: share(left_hashes.len()),
+        right_share: share(right_hashes.len()),
+        regions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLUTION: &str = r#"
+pub fn count_words(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            in_word = true;
+            count += 1;
+        }
+    }
+    count
+}
+"#;
+
+    const RENAMED: &str = r#"// My own solution, honestly
+pub fn count_words(s: &str) -> usize {
+    let mut n = 0; let mut inside = false;
+    for ch in s.chars() {
+        if ch.is_whitespace() { inside = false; }
+        else if !inside { inside = true; n += 1; }
+    }
+    n
+}
+"#;
+
+    const DIFFERENT: &str = r#"
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().filter(|word| !word.is_empty()).count()
+}
+"#;
+
+    fn document(content: &str) -> Document {
+        Document::new(vec![(PathBuf::from("src/lib.rs"), content.to_string())])
+    }
+
+    #[test]
+    fn finds_renamed_copies() {
+        let ignored = HashSet::new();
+        let copied = compare(&document(SOLUTION), &document(RENAMED), &ignored);
+        assert_eq!(copied.similarity(), 1.0);
+        assert_eq!(
+            copied.regions,
+            [Region {
+                left: (0, 2, 14),
+                right: (0, 2, 9)
+            }]
+        );
+
+        let different = compare(&document(SOLUTION), &document(DIFFERENT), &ignored);
+        assert_eq!(different.similarity(), 0.0);
+        assert!(different.regions.is_empty());
+    }
+
+    #[test]
+    fn ignores_template() {
+        let template = document(SOLUTION).hashes();
+        let comparison = compare(&document(SOLUTION), &document(RENAMED), &template);
+        assert_eq!(comparison.similarity(), 0.0);
+    }
+}
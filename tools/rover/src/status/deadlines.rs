@@ -0,0 +1,143 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::{collections::BTreeMap, fs::File, path::Path};
+
+/// Deadlines of the problems from a yml mapping of "GROUP/TITLE" or "GROUP"
+/// to the local time, e.g. `intro: 2023-09-27 23:59`.
+pub struct Deadlines {
+    deadlines: BTreeMap<String, DateTime<Local>>,
+}
+
+fn parse_deadline(text: &str) -> Result<DateTime<Local>> {
+    let text = text.trim();
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+        })
+        .with_context(|| {
+            format!("deadline \"{text}\" is not in \"YYYY-MM-DD\" or \"YYYY-MM-DD HH:MM\" form")
+        })?;
+    match Local.from_local_datetime(&naive).earliest() {
+        Some(deadline) => Ok(deadline),
+        None => bail!("deadline \"{text}\" does not exist in the local timezone"),
+    }
+}
+
+impl Deadlines {
+    pub fn from_yml(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("no deadlines file {path:?}"))?;
+        let raw: BTreeMap<String, String> = serde_yaml::from_reader(file)
+            .with_context(|| format!("invalid deadlines file {path:?}"))?;
+        let deadlines = raw
+            .into_iter()
+            .map(|(key, value)| {
+                let deadline = parse_deadline(&value)
+                    .with_context(|| format!("invalid deadline of {key} in {path:?}"))?;
+                Ok((key, deadline))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { deadlines })
+    }
+
+    /// The deadline of the problem itself or else of its group.
+    pub fn get(&self, group: &str, title: &str) -> Option<DateTime<Local>> {
+        self.deadlines
+            .get(&format!("{group}/{title}"))
+            .or_else(|| self.deadlines.get(group))
+            .copied()
+    }
+}
+
+/// Formats the deadline together with the time left, e.g. "2023-09-27 23:59 (in 2d 4h)".
+pub fn describe_deadline(deadline: DateTime<Local>, now: DateTime<Local>) -> String {
+    let date = deadline.format("%Y-%m-%d %H:%M");
+    let left = deadline - now;
+    if left.num_seconds() < 0 {
+        return format!("{date} (passed)");
+    }
+    let (days, hours, minutes) = (
+        left.num_days(),
+        left.num_hours() % 24,
+        left.num_minutes() % 60,
+    );
+    if days > 0 {
+        format!("{date} (in {days}d {hours}h)")
+    } else {
+        format!("{date} (in {hours}h {minutes}m)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn local(text: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn parses_deadlines() {
+        assert_eq!(
+            parse_deadline("2023-09-27 23:59").unwrap(),
+            local("2023-09-27 23:59:00")
+        );
+        assert_eq!(
+            parse_deadline(" 2023-09-27 12:30:15 ").unwrap(),
+            local("2023-09-27 12:30:15")
+        );
+        assert_eq!(
+            parse_deadline("2023-09-27").unwrap(),
+            local("2023-09-27 23:59:59")
+        );
+        for text in ["27.09.2023", "2023-09-27 25:00", "tomorrow", ""] {
+            let err = parse_deadline(text).unwrap_err();
+            assert!(err.to_string().contains("is not in"), "{text}: {err}");
+        }
+    }
+
+    #[test]
+    fn problems_fall_back_to_groups() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".deadlines.yml");
+        fs::write(&path, "intro: 2023-09-27 23:59\nintro/conway: 2023-10-04\n").unwrap();
+        let deadlines = Deadlines::from_yml(&path).unwrap();
+        assert_eq!(
+            deadlines.get("intro", "conway"),
+            Some(local("2023-10-04 23:59:59"))
+        );
+        assert_eq!(
+            deadlines.get("intro", "min-queue"),
+            Some(local("2023-09-27 23:59:00"))
+        );
+        assert_eq!(deadlines.get("borrowing", "bst"), None);
+
+        fs::write(&path, "intro: soon\n").unwrap();
+        let err = Deadlines::from_yml(&path).err().unwrap();
+        assert!(
+            err.to_string().contains("invalid deadline of intro"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn describes_time_left() {
+        let deadline = local("2023-09-27 23:59:00");
+        let describe = |left: Duration| describe_deadline(deadline, deadline - left);
+        assert_eq!(describe(Duration::minutes(-1)), "2023-09-27 23:59 (passed)");
+        assert_eq!(describe(Duration::zero()), "2023-09-27 23:59 (in 0h 0m)");
+        assert_eq!(
+            describe(Duration::minutes(3 * 60 + 5)),
+            "2023-09-27 23:59 (in 3h 5m)"
+        );
+        assert_eq!(
+            describe(Duration::hours(2 * 24 + 4) + Duration::minutes(30)),
+            "2023-09-27 23:59 (in 2d 4h)"
+        );
+    }
+}
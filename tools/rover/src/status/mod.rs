@@ -0,0 +1,2 @@
+mod deadlines;
+pub mod run_status;
@@ -0,0 +1,169 @@
+use super::deadlines::{describe_deadline, Deadlines};
+use crate::{
+    git::git_repo::GitRepo,
+    repository::{
+        problem::Problem,
+        repo::{Repository, DEADLINES_FILE},
+    },
+};
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+
+enum State {
+    NotStarted,
+    Modified,
+    Unpushed,
+    Submitted,
+    OutOfDate,
+}
+
+impl State {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "not started",
+            Self::Modified => "modified",
+            Self::Unpushed => "unpushed",
+            Self::Submitted => "submitted",
+            Self::OutOfDate => "out of date",
+        }
+    }
+}
+
+fn problem_state(
+    repository: &Repository,
+    course: Option<&GitRepo>,
+    solutions: Option<&GitRepo>,
+    problem: &Problem,
+) -> Result<State> {
+    let files = problem.solution_files()?;
+    let branch = problem.branch_name();
+    let root = repository.get_path();
+    if let Some(solutions) = solutions.filter(|solutions| solutions.has_branch(&branch)) {
+        // The remote branch is what was submitted, the local one may be not pushed yet
+        return Ok(
+            if solutions.has_remote_branch(&branch)
+                && solutions.matches_files(&solutions.remote_base(&branch)?, root, &files)?
+            {
+                State::Submitted
+            } else if solutions.has_unpushed_commits(&branch)?
+                && solutions.matches_files(&branch, root, &files)?
+            {
+                State::Unpushed
+            } else {
+                State::OutOfDate
+            },
+        );
+    }
+    let unchanged = match course {
+        Some(course) => course.matches_files("HEAD", root, &files)?,
+        None => true,
+    };
+    Ok(if unchanged {
+        State::NotStarted
+    } else {
+        State::Modified
+    })
+}
+
+pub fn run_status(
+    path: &Path,
+    solutions_repo: Option<PathBuf>,
+    deadlines: Option<PathBuf>,
+) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let course = GitRepo::open(repository.get_path()).ok();
+    let solutions_repo = match solutions_repo {
+        Some(path) => Some(path),
+        None => repository.solutions_repo().ok(),
+    };
+    let solutions = match &solutions_repo {
+        Some(path) => Some(GitRepo::open(path)?),
+        None => {
+            eprintln!(
+                "warning: solutions repository is not found, no problem is shown as submitted"
+            );
+            None
+        }
+    };
+    let deadlines = match deadlines {
+        Some(path) => Some(Deadlines::from_yml(&path)?),
+        None => {
+            let path = repository.get_path().join(DEADLINES_FILE);
+            path.is_file()
+                .then(|| Deadlines::from_yml(&path))
+                .transpose()?
+        }
+    };
+    let problems = repository.problems()?;
+    let now = Local::now();
+    let width = problems
+        .iter()
+        .map(|problem| problem.branch_name().len())
+        .max()
+        .unwrap_or(0);
+    println!("{:width$}  {:11}  deadline", "problem", "state");
+    for problem in &problems {
+        let state = problem_state(&repository, course.as_ref(), solutions.as_ref(), problem)
+            .with_context(|| format!("failed to get state of {}", problem.branch_name()))?;
+        let deadline = deadlines
+            .as_ref()
+            .and_then(|deadlines| deadlines.get(&problem.group(), &problem.title()))
+            .map(|deadline| describe_deadline(deadline, now))
+            .unwrap_or_default();
+        println!(
+            "{:width$}  {:11}  {deadline}",
+            problem.branch_name(),
+            state.name()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::git_repo::tests::{clone, origin};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn unpushed_commits_are_not_submitted() {
+        let root = TempDir::new().unwrap();
+        let course = root.path().join("course");
+        let problem_path = course.join("problems/tutorial/add");
+        fs::create_dir_all(problem_path.join("src")).unwrap();
+        fs::write(course.join(".rover.toml"), "").unwrap();
+        fs::write(
+            problem_path.join(".config.yml"),
+            "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
+        )
+        .unwrap();
+        fs::write(problem_path.join("src/lib.rs"), "pub fn add() {}").unwrap();
+        let url = origin(root.path(), "main");
+        let solutions_path = root.path().join("solutions");
+        let solutions = clone(&url, &solutions_path);
+        let repository = Repository::from_path(&course).unwrap();
+        let problem = repository.problem_from_path(&problem_path).unwrap();
+        let state = || {
+            problem_state(&repository, None, Some(&solutions), &problem)
+                .unwrap()
+                .name()
+        };
+        assert_eq!(state(), "not started");
+
+        solutions
+            .checkout_new_branch("tutorial/add", "origin/main")
+            .unwrap();
+        problem.move_solution_files_to(&solutions_path).unwrap();
+        solutions.add(&problem.solution_files().unwrap()).unwrap();
+        solutions.commit("solution").unwrap();
+        assert_eq!(state(), "unpushed");
+
+        solutions.push("tutorial/add").unwrap();
+        assert_eq!(state(), "submitted");
+
+        fs::write(problem_path.join("src/lib.rs"), "pub fn add() { todo!() }").unwrap();
+        assert_eq!(state(), "out of date");
+    }
+}
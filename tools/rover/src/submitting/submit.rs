@@ -1,14 +1,62 @@
-use crate::{launch_git, repository::repo::Repository};
-use anyhow::{bail, Result};
+use crate::{
+    git::git_repo::{describe_changes, diff_buffers, GitRepo},
+    repository::{context::LaunchOptions, repo::Repository},
+    testing::test::test_problem,
+};
+use anyhow::{bail, Context, Result};
 use std::{
+    fs,
     path::{Path, PathBuf},
-    process,
 };
 
+pub struct SubmitOptions {
+    /// Only show what would be submitted.
+    pub dry_run: bool,
+    /// Run the testing steps locally before committing.
+    pub verify: bool,
+    /// Names of the steps to run, all of them if empty.
+    pub verify_steps: Vec<String>,
+}
+
+/// Prints how the files differ from `base` and returns whether any of them does.
+fn print_changes(
+    repository: &Repository,
+    git: &GitRepo,
+    base: &str,
+    files: &[PathBuf],
+    show_diff: bool,
+) -> Result<bool> {
+    println!("Files to submit, compared to {base}:");
+    let mut changed = false;
+    for file in files {
+        let new = fs::read(repository.get_path().join(file))
+            .with_context(|| format!("failed to read {file:?}"))?;
+        let old = git.read_file(base, file)?;
+        if old.as_ref() == Some(&new) {
+            println!("  {} (unchanged)", file.display());
+            continue;
+        }
+        changed = true;
+        let diff = diff_buffers(file, old.as_deref().unwrap_or_default(), &new)?;
+        let status = if old.is_some() { "modified" } else { "new" };
+        println!(
+            "  {} ({status}, +{} -{})",
+            file.display(),
+            diff.additions,
+            diff.deletions
+        );
+        if show_diff {
+            print!("{}", diff.text);
+        }
+    }
+    Ok(changed)
+}
+
 pub fn submit_problem(
     problem_path: &Path,
     message: &str,
     solutions_repo: Option<PathBuf>,
+    options: &SubmitOptions,
 ) -> Result<()> {
     let repository = Repository::from_path(problem_path)?;
     let problem = repository.problem_from_path(problem_path)?;
@@ -16,23 +64,167 @@ pub fn submit_problem(
         Some(path) => path,
         None => repository.solutions_repo()?,
     };
-    problem.move_solution_files_to(&solutions_repo)?;
-    if !launch_git!(&solutions_repo, "add", ".") {
-        bail!("git add failed");
-    }
-    if !launch_git!(&solutions_repo, "commit", "-m", message) {
-        bail!("git commit failed: either no changes since the last commit or git failed")
-    }
-    if !launch_git!(&solutions_repo, "push")
-        && !launch_git!(
-            &solutions_repo,
-            "push",
-            "--set-upstream",
-            "origin",
-            problem.branch_name()
+    let git = GitRepo::open(&solutions_repo)?;
+    let branch = problem.branch_name();
+    let files = problem.solution_files()?;
+    let unrelated: Vec<_> = git
+        .changes()?
+        .into_iter()
+        .filter(|change| !files.contains(&change.path))
+        .collect();
+    // The files are compared with what the remote has, as the last push may have failed
+    let base = git.remote_base(&branch)?;
+    let changed = print_changes(&repository, &git, &base, &files, options.dry_run)?;
+    let committed = git.has_unpushed_commits(&branch)?
+        && git.matches_files(&branch, repository.get_path(), &files)?;
+    if options.dry_run {
+        if !unrelated.is_empty() {
+            println!(
+                "\nSubmit would refuse to commit, since unrelated files are dirty: {}",
+                describe_changes(&unrelated)
+            );
+        }
+        if committed {
+            println!("\nThe solution is already committed to {branch}, submit would only push it");
+        }
+        println!("\nDry run, nothing was copied or committed");
+        return Ok(());
+    }
+    if !unrelated.is_empty() {
+        bail!(
+            "unrelated files are dirty in {solutions_repo:?}: {}; commit, stash or remove them first",
+            describe_changes(&unrelated)
+        )
+    }
+    if !changed {
+        bail!("nothing to submit, the files are the same as on {base}")
+    }
+    if options.verify {
+        let launch_options = LaunchOptions {
+            fail_fast: true,
+            steps: options.verify_steps.clone(),
+            ..Default::default()
+        };
+        let result = test_problem(&problem, &launch_options)?;
+        if result.failed {
+            bail!(
+                "local checks failed: {}; fix them or submit with --no-verify",
+                result.failure_summary()
+            )
+        }
+    }
+    if committed {
+        println!("The solution is already committed to {branch}, pushing it");
+    } else {
+        problem.move_solution_files_to(&solutions_repo)?;
+        git.add(&files)?;
+        git.commit(message)?;
+    }
+    git.push(&branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::git_repo::tests::{clone, origin};
+    use git2::Repository as GitRepository;
+    use tempfile::TempDir;
+
+    const PROBLEM: &str = "problems/tutorial/add";
+
+    /// Creates a course with a single problem and a clone of an empty solutions repository.
+    fn course(root: &Path) -> (PathBuf, PathBuf, String) {
+        let course = root.join("course");
+        let problem = course.join(PROBLEM);
+        fs::create_dir_all(problem.join("src")).unwrap();
+        fs::write(course.join(".rover.toml"), "").unwrap();
+        fs::write(
+            problem.join(".config.yml"),
+            "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
         )
-    {
-        bail!("git push failed")
+        .unwrap();
+        fs::write(problem.join("src/lib.rs"), "pub fn add() {}").unwrap();
+        let url = origin(root, "main");
+        let solutions = root.join("solutions");
+        clone(&url, &solutions);
+        (problem, solutions, url)
+    }
+
+    fn options() -> SubmitOptions {
+        SubmitOptions {
+            dry_run: false,
+            verify: false,
+            verify_steps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn commits_to_problem_branch() {
+        let root = TempDir::new().unwrap();
+        let (problem, solutions, url) = course(root.path());
+        let dry_run = SubmitOptions {
+            dry_run: true,
+            ..options()
+        };
+        submit_problem(&problem, "first", Some(solutions.clone()), &dry_run).unwrap();
+        let origin = GitRepository::open_bare(&url).unwrap();
+        assert!(origin.find_reference("refs/heads/tutorial/add").is_err());
+
+        submit_problem(&problem, "first", Some(solutions.clone()), &options()).unwrap();
+        let commit = origin
+            .find_reference("refs/heads/tutorial/add")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.message(), Some("first"));
+        assert_eq!(commit.parent(0).unwrap().message(), Some("init"));
+        let path = Path::new(PROBLEM).join("src/lib.rs");
+        let blob = commit.tree().unwrap().get_path(&path).unwrap();
+        assert_eq!(
+            blob.to_object(&origin)
+                .unwrap()
+                .as_blob()
+                .unwrap()
+                .content(),
+            b"pub fn add() {}"
+        );
+
+        let git = GitRepo::open(&solutions).unwrap();
+        assert_eq!(
+            git.current_branch().unwrap().as_deref(),
+            Some("tutorial/add")
+        );
+        let err =
+            submit_problem(&problem, "again", Some(solutions.clone()), &options()).unwrap_err();
+        assert!(err.to_string().starts_with("nothing to submit"), "{err}");
+
+        fs::write(problem.join("src/lib.rs"), "pub fn add() { todo!() }").unwrap();
+        submit_problem(&problem, "second", Some(solutions), &options()).unwrap();
+        let commit = origin
+            .find_reference("refs/heads/tutorial/add")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.message(), Some("second"));
+        assert_eq!(commit.parent(0).unwrap().message(), Some("first"));
+    }
+
+    #[test]
+    fn pushes_commit_left_by_failed_push() {
+        let root = TempDir::new().unwrap();
+        let (problem, solutions, url) = course(root.path());
+        let git = GitRepository::open(&solutions).unwrap();
+        git.remote_set_url("origin", "/nonexistent").unwrap();
+        assert!(submit_problem(&problem, "first", Some(solutions.clone()), &options()).is_err());
+
+        git.remote_set_url("origin", &url).unwrap();
+        submit_problem(&problem, "retry", Some(solutions), &options()).unwrap();
+        let origin = GitRepository::open_bare(&url).unwrap();
+        let commit = origin
+            .find_reference("refs/heads/tutorial/add")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.message(), Some("first"));
     }
-    Ok(())
 }
@@ -0,0 +1,174 @@
+use super::{cache::TestCache, result::TestingResult};
+use crate::repository::{context::LaunchOptions, problem::Problem, repo::Repository};
+use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub enum ProblemStatus {
+    Passed,
+    Failed,
+    Cached,
+    Error(String),
+}
+
+pub struct ProblemRun {
+    pub problem: String,
+    pub status: ProblemStatus,
+    pub duration: Duration,
+    pub result: Option<TestingResult>,
+}
+
+impl ProblemRun {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.status, ProblemStatus::Passed | ProblemStatus::Cached)
+    }
+}
+
+fn run_problem(
+    problem: &Problem,
+    cache: &Mutex<TestCache>,
+    use_cache: bool,
+    options: &LaunchOptions,
+) -> ProblemRun {
+    let name = problem.branch_name();
+    let start = Instant::now();
+    let fingerprint = match problem.fingerprint() {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            return ProblemRun {
+                problem: name,
+                status: ProblemStatus::Error(format!("{err:#}")),
+                duration: start.elapsed(),
+                result: None,
+            }
+        }
+    };
+    if use_cache && cache.lock().unwrap().is_fresh(&name, &fingerprint) {
+        return ProblemRun {
+            problem: name,
+            status: ProblemStatus::Cached,
+            duration: Duration::ZERO,
+            result: None,
+        };
+    }
+    let (status, result) = match problem.launch_all_steps(options) {
+        Ok(result) if result.failed => {
+            cache.lock().unwrap().forget(&name);
+            (ProblemStatus::Failed, Some(result))
+        }
+        Ok(result) => {
+            cache.lock().unwrap().record(&name, fingerprint);
+            (ProblemStatus::Passed, Some(result))
+        }
+        Err(err) => (ProblemStatus::Error(format!("{err:#}")), None),
+    };
+    ProblemRun {
+        problem: name,
+        status,
+        duration: start.elapsed(),
+        result,
+    }
+}
+
+fn print_failure(run: &ProblemRun) {
+    match (&run.status, &run.result) {
+        (ProblemStatus::Error(err), _) => eprintln!("{}: {err}", run.problem),
+        (ProblemStatus::Failed, Some(result)) => {
+            for (step, command) in result.failed_commands() {
+                eprintln!("---- {} {}/{} ----", run.problem, step.name, command.name);
+                eprint!("{}{}", command.stdout, command.stderr);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn print_table(runs: &[ProblemRun]) {
+    let width = runs.iter().map(|run| run.problem.len()).max().unwrap_or(0);
+    println!(
+        "\n{:width$}  {:8}  {:>8}  {:>6}  failed",
+        "problem", "status", "time", "score"
+    );
+    for run in runs {
+        let status = match run.status {
+            ProblemStatus::Passed => "passed",
+            ProblemStatus::Failed => "FAILED",
+            ProblemStatus::Cached => "cached",
+            ProblemStatus::Error(_) => "ERROR",
+        };
+        let failed = run
+            .result
+            .as_ref()
+            .map(|result| result.failure_summary())
+            .unwrap_or_default();
+        let score = match &run.result {
+            Some(result) => format!("{:.1}%", result.score * 100.0),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:width$}  {:8}  {:>7.1}s  {:>6}  {}",
+            run.problem,
+            status,
+            run.duration.as_secs_f64(),
+            score,
+            failed
+        );
+    }
+    let ok = runs.iter().filter(|run| run.is_ok()).count();
+    println!("\n{ok} of {} problems passed", runs.len());
+}
+
+/// Tests every problem of the repository, running up to `jobs` problems at once.
+pub fn test_all_problems(
+    repository: &Repository,
+    jobs: usize,
+    use_cache: bool,
+    options: &LaunchOptions,
+) -> Result<Vec<ProblemRun>> {
+    let problems = repository.problems()?;
+    let total = problems.len();
+    let target_dir = repository.target_dir();
+    let cache = Mutex::new(TestCache::load(&target_dir));
+    let queue = Mutex::new(problems.into_iter().collect::<VecDeque<_>>());
+    let runs = Mutex::new(Vec::with_capacity(total));
+    // The workers share the target directory, so the dependencies are built once,
+    // cargo's lock on it keeps the builds running at once from clashing
+    let options = LaunchOptions {
+        quiet: true,
+        target_dir: Some(target_dir.clone()),
+        ..options.clone()
+    };
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            let (cache, queue, runs, options) = (&cache, &queue, &runs, &options);
+            scope.spawn(move || loop {
+                let problem = match queue.lock().unwrap().pop_front() {
+                    Some(problem) => problem,
+                    None => break,
+                };
+                let run = run_problem(&problem, cache, use_cache, options);
+                let mut runs = runs.lock().unwrap();
+                println!(
+                    "[{}/{total}] {} ... {}",
+                    runs.len() + 1,
+                    run.problem,
+                    match run.status {
+                        ProblemStatus::Passed => "ok",
+                        ProblemStatus::Cached => "ok (cached)",
+                        ProblemStatus::Failed | ProblemStatus::Error(_) => "FAILED",
+                    }
+                );
+                print_failure(&run);
+                runs.push(run);
+            });
+        }
+    });
+    cache.into_inner().unwrap().save()?;
+    let mut runs = runs.into_inner().unwrap();
+    runs.sort_by(|a, b| a.problem.cmp(&b.problem));
+    Ok(runs)
+}
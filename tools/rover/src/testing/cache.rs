@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const CACHE_FILE: &str = "rover/test-cache.json";
+
+/// Fingerprints of the problems as of their last successful testing run.
+pub struct TestCache {
+    path: PathBuf,
+    entries: BTreeMap<String, String>,
+}
+
+impl TestCache {
+    pub fn load(target_dir: &Path) -> Self {
+        let path = target_dir.join(CACHE_FILE);
+        // A missing or broken cache only means that everything is tested again
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    pub fn is_fresh(&self, problem: &str, fingerprint: &str) -> bool {
+        self.entries.get(problem).map(String::as_str) == Some(fingerprint)
+    }
+
+    pub fn record(&mut self, problem: &str, fingerprint: String) {
+        self.entries.insert(problem.to_string(), fingerprint);
+    }
+
+    pub fn forget(&mut self, problem: &str) {
+        self.entries.remove(problem);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(self.path.parent().context("cache path has no parent")?)
+            .context("failed to create cache directory")?;
+        let content =
+            serde_json::to_string_pretty(&self.entries).context("failed to serialize cache")?;
+        fs::write(&self.path, content).with_context(|| format!("failed to write {:?}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::hash::hash_files;
+
+    #[test]
+    fn fingerprints_follow_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "fn a() {}").unwrap();
+        fs::write(dir.path().join(".config.yml"), "steps: {}").unwrap();
+        let files = [PathBuf::from("src/lib.rs"), PathBuf::from(".config.yml")];
+        let key = || hash_files(dir.path(), &files).unwrap();
+        let original = key();
+        let reversed = [files[1].clone(), files[0].clone()];
+        assert_eq!(hash_files(dir.path(), &reversed).unwrap(), original);
+
+        let target_dir = dir.path().join("target");
+        let mut cache = TestCache::load(&target_dir);
+        assert!(!cache.is_fresh("intro/add", &original));
+        cache.record("intro/add", original.clone());
+        cache.save().unwrap();
+        let mut cache = TestCache::load(&target_dir);
+        assert!(cache.is_fresh("intro/add", &original));
+        assert!(!cache.is_fresh("intro/sub", &original));
+
+        fs::write(dir.path().join("src/lib.rs"), "fn b() {}").unwrap();
+        assert!(!cache.is_fresh("intro/add", &key()));
+        fs::write(dir.path().join("src/lib.rs"), "fn a() {}").unwrap();
+        assert!(cache.is_fresh("intro/add", &key()));
+        fs::write(dir.path().join(".config.yml"), "steps: {x: []}").unwrap();
+        assert!(!cache.is_fresh("intro/add", &key()));
+
+        cache.forget("intro/add");
+        cache.save().unwrap();
+        assert!(!TestCache::load(&target_dir).is_fresh("intro/add", &original));
+    }
+
+    #[test]
+    fn broken_cache_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("rover")).unwrap();
+        fs::write(dir.path().join(CACHE_FILE), "{\"intro/add\": ").unwrap();
+        assert!(!TestCache::load(dir.path()).is_fresh("intro/add", ""));
+    }
+}
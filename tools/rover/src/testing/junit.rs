@@ -0,0 +1,199 @@
+use super::result::{Outcome, TestingResult};
+use anyhow::{Context, Result};
+use std::{fmt::Write, fs, path::Path};
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // XML 1.0 does not allow most control characters, e.g. terminal escape codes
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn count(results: &[&TestingResult], outcome: Option<Outcome>) -> usize {
+    results
+        .iter()
+        .flat_map(|result| result.steps.iter())
+        .flat_map(|step| step.commands.iter())
+        .filter(|command| outcome.is_none_or(|outcome| command.outcome == outcome))
+        .count()
+}
+
+pub fn to_junit(results: &[&TestingResult]) -> String {
+    let mut xml = String::new();
+    let duration: f64 = results
+        .iter()
+        .map(|result| result.duration.as_secs_f64())
+        .sum();
+    // Writing to a String never fails
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="rover" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+        count(results, None),
+        count(results, Some(Outcome::Failed)) + count(results, Some(Outcome::TimedOut)),
+        count(results, Some(Outcome::Skipped)),
+        duration,
+    );
+    for result in results {
+        write_problem(&mut xml, result);
+    }
+    let _ = writeln!(xml, "</testsuites>");
+    xml
+}
+
+fn write_problem(xml: &mut String, result: &TestingResult) {
+    for step in &result.steps {
+        let failures = step
+            .commands
+            .iter()
+            .filter(|command| command.outcome.is_failure())
+            .count();
+        let skipped = step
+            .commands
+            .iter()
+            .filter(|command| command.outcome == Outcome::Skipped)
+            .count();
+        let _ = writeln!(
+            xml,
+            r#"  <testsuite name="{}/{}" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+            escape(&result.problem),
+            escape(&step.name),
+            step.commands.len(),
+            failures,
+            skipped,
+            step.duration.as_secs_f64(),
+        );
+        for command in &step.commands {
+            let _ = writeln!(
+                xml,
+                r#"    <testcase name="{}" classname="{}.{}" time="{:.3}">"#,
+                escape(&command.name),
+                escape(&result.problem),
+                escape(&step.name),
+                command.duration.as_secs_f64(),
+            );
+            match command.outcome {
+                Outcome::Passed => {}
+                Outcome::Skipped => {
+                    let _ = writeln!(xml, "      <skipped/>");
+                }
+                Outcome::TimedOut => {
+                    let _ = writeln!(
+                        xml,
+                        r#"      <failure message="timed out">{}</failure>"#,
+                        escape(&command.command),
+                    );
+                }
+                Outcome::Failed => {
+                    let message = match command.exit_code {
+                        Some(code) => format!("exited with code {code}"),
+                        None => "failed".to_string(),
+                    };
+                    let _ = writeln!(
+                        xml,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape(&message),
+                        escape(&command.command),
+                    );
+                }
+            }
+            if !command.stdout.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-out>{}</system-out>",
+                    escape(&command.stdout)
+                );
+            }
+            if !command.stderr.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-err>{}</system-err>",
+                    escape(&command.stderr)
+                );
+            }
+            let _ = writeln!(xml, "    </testcase>");
+        }
+        let _ = writeln!(xml, "  </testsuite>");
+    }
+}
+
+pub fn write_junit(results: &[&TestingResult], path: &Path) -> Result<()> {
+    fs::write(path, to_junit(results))
+        .with_context(|| format!("failed to write report to {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result::{CommandResult, StepResult};
+    use std::time::Duration;
+
+    fn command(name: &str, outcome: Outcome, exit_code: Option<i32>) -> CommandResult {
+        CommandResult {
+            name: name.to_string(),
+            command: format!("cargo {name}"),
+            duration: Duration::from_millis(1500),
+            outcome,
+            exit_code,
+            stdout: String::new(),
+            stderr: String::new(),
+            tests: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn maps_outcomes_and_escapes() {
+        let mut failed = command("test <debug>", Outcome::Failed, Some(101));
+        failed.stderr = "assertion `a == \"b\"` & 'c'\x1b[0m\n".to_string();
+        let result = TestingResult {
+            problem: "tutorial/add".to_string(),
+            duration: Duration::from_secs(3),
+            failed: true,
+            score: 0.0,
+            steps: vec![StepResult::new(
+                "tests".to_string(),
+                vec![
+                    command("build", Outcome::Passed, Some(0)),
+                    failed,
+                    command("test --release", Outcome::TimedOut, None),
+                    command("miri", Outcome::Skipped, None),
+                ],
+            )],
+        };
+        let xml = to_junit(&[&result]);
+        assert!(xml.contains(
+            r#"<testsuites name="rover" tests="4" failures="2" skipped="1" time="3.000">"#
+        ));
+        assert!(xml.contains(
+            r#"<testsuite name="tutorial/add/tests" tests="4" failures="2" skipped="1" time="6.000">"#
+        ));
+        assert!(xml.contains(
+            r#"<testcase name="test &lt;debug&gt;" classname="tutorial/add.tests" time="1.500">"#
+        ));
+        assert!(xml.contains(
+            r#"<failure message="exited with code 101">cargo test &lt;debug&gt;</failure>"#
+        ));
+        assert!(xml.contains(
+            "<system-err>assertion `a == &quot;b&quot;` &amp; &apos;c&apos;[0m\n</system-err>"
+        ));
+        assert!(xml.contains(r#"<failure message="timed out">cargo test --release</failure>"#));
+        assert!(xml.contains(
+            "<testcase name=\"miri\" classname=\"tutorial/add.tests\" time=\"1.500\">\n      <skipped/>\n    </testcase>"
+        ));
+        let build = xml.find(r#"<testcase name="build""#).unwrap();
+        assert!(xml[build..].starts_with(
+            "<testcase name=\"build\" classname=\"tutorial/add.tests\" time=\"1.500\">\n    </testcase>"
+        ));
+    }
+}
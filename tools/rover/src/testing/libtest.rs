@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// A single test reported by the libtest harness.
+#[derive(Serialize, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+}
+
+/// An event of libtest's `--format json` output.
+#[derive(Deserialize)]
+struct JsonEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+}
+
+fn parse_json_line(line: &str) -> Option<TestCase> {
+    let event: JsonEvent = serde_json::from_str(line).ok()?;
+    if event.kind != "test" {
+        return None;
+    }
+    let outcome = match event.event.as_str() {
+        "ok" => TestOutcome::Passed,
+        "failed" | "timeout" => TestOutcome::Failed,
+        "ignored" => TestOutcome::Ignored,
+        _ => return None,
+    };
+    Some(TestCase {
+        name: event.name?,
+        outcome,
+    })
+}
+
+/// Parses lines like `test tests::it_works ... ok`.
+fn parse_text_line(line: &str) -> Option<TestCase> {
+    let (name, result) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+    let outcome = match result.trim_end() {
+        "ok" => TestOutcome::Passed,
+        "FAILED" => TestOutcome::Failed,
+        result if result.starts_with("ignored") => TestOutcome::Ignored,
+        _ => return None,
+    };
+    Some(TestCase {
+        name: name.trim().to_string(),
+        outcome,
+    })
+}
+
+/// Collects the results of the tests from the output of `cargo test`,
+/// either in the default or in the JSON format.
+pub fn parse_test_output(stdout: &str) -> Vec<TestCase> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            if line.starts_with('{') {
+                parse_json_line(line)
+            } else {
+                parse_text_line(line)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_test_output, TestOutcome};
+
+    #[test]
+    fn parses_text_and_json_output() {
+        let stdout = r#"
+running 4 tests
+test tests::it_works ... ok
+test tests::big ... FAILED
+test tests::slow ... ignored, takes too long
+test src/lib.rs - add (line 3) ... ok
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+{ "type": "test", "event": "started", "name": "json::first" }
+{ "type": "test", "name": "json::first", "event": "ok" }
+{ "type": "test", "name": "json::second", "event": "failed", "stdout": "" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1 }
+"#;
+        let tests: Vec<_> = parse_test_output(stdout)
+            .into_iter()
+            .map(|test| (test.name, test.outcome))
+            .collect();
+        assert_eq!(
+            tests,
+            [
+                ("tests::it_works".to_string(), TestOutcome::Passed),
+                ("tests::big".to_string(), TestOutcome::Failed),
+                ("tests::slow".to_string(), TestOutcome::Ignored),
+                ("src/lib.rs - add (line 3)".to_string(), TestOutcome::Passed),
+                ("json::first".to_string(), TestOutcome::Passed),
+                ("json::second".to_string(), TestOutcome::Failed),
+            ]
+        );
+    }
+}
@@ -1,2 +1,8 @@
+pub mod all;
+mod cache;
+pub mod junit;
+pub mod libtest;
 pub mod report;
+pub mod result;
 pub mod test;
+pub mod watch;
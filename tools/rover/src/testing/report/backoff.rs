@@ -0,0 +1,58 @@
+use anyhow::{bail, Result};
+use reqwest::blocking::Response;
+use std::{thread, time::Duration};
+
+/// Retries requests failed on the network or with a 5xx status, doubling the delay each time.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub attempts: usize,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl Backoff {
+    pub fn send(
+        &self,
+        target: &str,
+        mut request: impl FnMut() -> reqwest::Result<Response>,
+    ) -> Result<Response> {
+        let mut delay = self.initial_delay;
+        let mut last_error = String::new();
+        for attempt in 1..=self.attempts {
+            match request() {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Ok(response) => last_error = format!("status {}", response.status()),
+                Err(err) => last_error = err.to_string(),
+            }
+            if attempt < self.attempts {
+                eprintln!("Report to {target} failed ({last_error}), retrying in {delay:?}");
+                thread::sleep(delay);
+                delay = (delay * 2).min(self.max_delay);
+            }
+        }
+        bail!(
+            "{} attempts to report to {target} failed, the last one with {last_error}",
+            self.attempts
+        )
+    }
+}
+
+/// Turns a 4xx response into an error, since retrying it won't help.
+pub fn check_status(target: &str, response: Response) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().unwrap_or_default();
+    bail!("{target} rejected the report with status {status}: {body}")
+}
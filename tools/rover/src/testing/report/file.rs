@@ -0,0 +1,35 @@
+use super::{Report, Reporter};
+use anyhow::{Context, Result};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Appends the reports to a local file as JSON lines.
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+pub(super) fn append(path: &Path, content: &str) -> Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .with_context(|| format!("failed to write report to {path:?}"))
+}
+
+impl Reporter for FileSink {
+    fn push_report(&self, report: &Report) -> Result<()> {
+        let mut line = serde_json::to_string(report).context("failed to serialize report")?;
+        line.push('\n');
+        append(&self.path, &line)
+    }
+}
@@ -0,0 +1,60 @@
+use super::{file::append, Report, Reporter};
+use crate::testing::test::outcome_mark;
+use anyhow::{bail, Result};
+use std::{env, path::Path};
+
+/// Reports through the files and workflow commands of GitHub Actions.
+pub struct GithubActions;
+
+fn summary(report: &Report) -> String {
+    let mark = if report.failed { "failed" } else { "passed" };
+    let mut summary = format!("### Testing of {} {mark}\n\n", report.problem);
+    if let Some(score) = report.score {
+        summary += &format!("Score: {:.1}%\n\n", score * 100.0);
+    }
+    if let Some(result) = report.result {
+        summary += "| Step | Command | Result | Time |\n|---|---|---|---|\n";
+        for step in &result.steps {
+            for command in &step.commands {
+                summary += &format!(
+                    "| {} | {} | {} | {:.1}s |\n",
+                    step.name,
+                    command.name,
+                    outcome_mark(command.outcome),
+                    command.duration.as_secs_f64()
+                );
+            }
+        }
+    }
+    summary
+}
+
+impl Reporter for GithubActions {
+    fn push_report(&self, report: &Report) -> Result<()> {
+        if env::var("GITHUB_ACTIONS").as_deref() != Ok("true") {
+            bail!("github report works only in GitHub Actions, GITHUB_ACTIONS is not set")
+        }
+        if let Some(result) = report.result {
+            for (step, command) in result.failed_commands() {
+                println!(
+                    "::error title=Testing of {} failed::{}/{} is {}",
+                    report.problem,
+                    step.name,
+                    command.name,
+                    outcome_mark(command.outcome)
+                );
+            }
+        }
+        if let Ok(path) = env::var("GITHUB_STEP_SUMMARY") {
+            append(Path::new(&path), &summary(report))?;
+        }
+        if let Ok(path) = env::var("GITHUB_OUTPUT") {
+            let mut output = format!("problem={}\nfailed={}\n", report.problem, report.failed);
+            if let Some(score) = report.score {
+                output += &format!("score={score}\n");
+            }
+            append(Path::new(&path), &output)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use super::{
+    backoff::{check_status, Backoff},
+    Report, Reporter,
+};
+use anyhow::{Context, Result};
+use reqwest::blocking::{multipart::Form, Client};
+use std::env;
+
+const MANYTASK_URL: &str = "https://mipt-rust.manytask.org/api/report";
+
+pub struct Manytask {
+    url: String,
+    backoff: Backoff,
+}
+
+impl Manytask {
+    pub fn new(url: Option<String>) -> Self {
+        Self {
+            url: url.unwrap_or_else(|| MANYTASK_URL.to_string()),
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+/// The task is the title of the problem, the branch of the CI job is used only
+/// when the problem is unknown.
+fn task_name(problem: &str) -> Result<String> {
+    if let Some((_, title)) = problem
+        .split_once('/')
+        .filter(|(_, title)| !title.is_empty())
+    {
+        return Ok(title.to_string());
+    }
+    env::var("CI_COMMIT_REF_NAME")
+        .context("no CI_COMMIT_REF_NAME variable")?
+        .split('/')
+        .nth(1)
+        .context("CI_COMMIT_REF_NAME does not contain '/' symbol")
+        .map(str::to_owned)
+}
+
+impl Reporter for Manytask {
+    fn push_report(&self, report: &Report) -> Result<()> {
+        if env::var("SKIP_REPORT").is_ok() {
+            return Ok(());
+        }
+        let task_name = task_name(&report.problem)?;
+        let user_id = env::var("GITLAB_USER_ID").context("no GITLAB_USER_ID variable")?;
+        let tester_token = env::var("TESTER_TOKEN").context("no TESTER_TOKEN variable")?;
+        let client = Client::new();
+        let response = self.backoff.send("manytask", || {
+            let mut data = Form::new()
+                .text("user_id", user_id.clone())
+                .text("task", task_name.clone())
+                .text("token", tester_token.clone());
+            if report.failed {
+                data = data.text("failed", "1");
+            }
+            // Manytask scales the share of the score by the maximum score of the task
+            if let Some(score) = report.score {
+                data = data.text("score", format!("{score:.4}"));
+            }
+            client.post(&self.url).multipart(data).send()
+        })?;
+        check_status("manytask", response)
+    }
+}
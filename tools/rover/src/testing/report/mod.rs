@@ -0,0 +1,273 @@
+mod backoff;
+mod file;
+mod github;
+mod manytask;
+mod webhook;
+
+use super::result::TestingResult;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::{env, path::PathBuf};
+
+/// What is known about a testing run when it is reported.
+#[derive(Serialize, Debug)]
+pub struct Report<'a> {
+    /// Name of the problem in the "GROUP/TITLE" form.
+    pub problem: String,
+    pub user: Option<String>,
+    pub failed: bool,
+    /// Score from 0 to 1, if testing has finished.
+    pub score: Option<f64>,
+    pub result: Option<&'a TestingResult>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(problem: String, failed: bool, result: Option<&'a TestingResult>) -> Self {
+        Self {
+            problem,
+            user: ci_user(),
+            failed,
+            score: result.map(|result| result.score),
+            result,
+        }
+    }
+}
+
+/// The user who triggered the CI job, if rover runs in a known CI.
+fn ci_user() -> Option<String> {
+    ["GITHUB_ACTOR", "GITLAB_USER_LOGIN", "GITLAB_USER_ID"]
+        .into_iter()
+        .find_map(|var| env::var(var).ok())
+}
+
+/// Settings of the report backends given on the command line.
+#[derive(Default)]
+pub struct ReportOptions {
+    pub url: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub fields: Vec<(String, String)>,
+    pub file: Option<PathBuf>,
+}
+
+impl ReportOptions {
+    /// Parses "NAME: VALUE" header.
+    pub fn parse_header(header: &str) -> Result<(String, String)> {
+        let (name, value) = header
+            .split_once(':')
+            .with_context(|| format!("header \"{header}\" is not in \"NAME: VALUE\" form"))?;
+        Ok((name.trim().to_string(), value.trim().to_string()))
+    }
+
+    /// Parses "KEY=VALUE" extra field of the report body.
+    pub fn parse_field(field: &str) -> Result<(String, String)> {
+        let (key, value) = field
+            .split_once('=')
+            .with_context(|| format!("field \"{field}\" is not in \"KEY=VALUE\" form"))?;
+        Ok((key.trim().to_string(), value.to_string()))
+    }
+}
+
+pub trait Reporter {
+    fn push_report(&self, report: &Report) -> Result<()>;
+}
+
+struct NoReport;
+
+impl Reporter for NoReport {
+    fn push_report(&self, _report: &Report) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub fn reporter_from_name(name: &str, options: ReportOptions) -> Result<Box<dyn Reporter>> {
+    Ok(match name {
+        "no-report" => Box::new(NoReport),
+        "manytask" => Box::new(manytask::Manytask::new(options.url)),
+        "webhook" => Box::new(webhook::Webhook::new(
+            options
+                .url
+                .context("webhook report requires --report-url")?,
+            options.headers,
+            options.fields,
+        )),
+        "file" => Box::new(file::FileSink::new(
+            options.file.context("file report requires --report-file")?,
+        )),
+        "github" => Box::new(github::GithubActions),
+        name => bail!("report type \"{name}\" is not supported"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        backoff::Backoff, file::FileSink, manytask::Manytask, webhook::Webhook, Report,
+        ReportOptions, Reporter,
+    };
+    use serde_json::Value;
+    use std::{sync::mpsc, thread, time::Duration};
+    use tiny_http::{Response, Server};
+
+    struct Request {
+        headers: Vec<(String, String)>,
+        body: String,
+    }
+
+    /// Answers the requests with the given statuses in order and sends them back.
+    fn mock_server(statuses: Vec<u16>) -> (String, mpsc::Receiver<Request>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/report", server.server_addr().to_ip().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let headers = request
+                    .headers()
+                    .iter()
+                    .map(|header| (header.field.to_string(), header.value.to_string()))
+                    .collect();
+                sender.send(Request { headers, body }).unwrap();
+                request
+                    .respond(Response::from_string("mock").with_status_code(status))
+                    .unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn fast_backoff(attempts: usize) -> Backoff {
+        Backoff {
+            attempts,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+        }
+    }
+
+    fn webhook(url: String, attempts: usize) -> Webhook {
+        let mut webhook = Webhook::new(
+            url,
+            vec![("X-Token".to_string(), "secret".to_string())],
+            vec![("course".to_string(), "rust".to_string())],
+        );
+        webhook.backoff = fast_backoff(attempts);
+        webhook
+    }
+
+    fn report() -> Report<'static> {
+        Report {
+            problem: "tutorial/add".to_string(),
+            user: Some("student".to_string()),
+            failed: true,
+            score: Some(0.5),
+            result: None,
+        }
+    }
+
+    #[test]
+    fn webhook_posts_json_with_headers_and_fields() {
+        let (url, requests) = mock_server(vec![200]);
+        webhook(url, 1).push_report(&report()).unwrap();
+        let request = requests.recv().unwrap();
+        let header = |name: &str| {
+            request
+                .headers
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(header("x-token"), Some("secret"));
+        assert_eq!(header("content-type"), Some("application/json"));
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["problem"], "tutorial/add");
+        assert_eq!(body["user"], "student");
+        assert_eq!(body["failed"], true);
+        assert_eq!(body["score"], 0.5);
+        assert_eq!(body["course"], "rust");
+    }
+
+    #[test]
+    fn server_errors_are_retried() {
+        let (url, requests) = mock_server(vec![500, 503, 200]);
+        webhook(url, 5).push_report(&report()).unwrap();
+        assert_eq!(requests.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn retries_are_limited() {
+        let (url, requests) = mock_server(vec![502, 502, 502]);
+        let err = webhook(url, 3).push_report(&report()).unwrap_err();
+        assert!(err.to_string().contains("3 attempts"), "{err}");
+        assert_eq!(requests.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let (url, requests) = mock_server(vec![403, 200]);
+        let err = webhook(url, 5).push_report(&report()).unwrap_err();
+        assert!(err.to_string().contains("403"), "{err}");
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn network_errors_are_retried() {
+        let url = {
+            let server = Server::http("127.0.0.1:0").unwrap();
+            format!("http://{}/report", server.server_addr().to_ip().unwrap())
+        };
+        let err = webhook(url, 2).push_report(&report()).unwrap_err();
+        assert!(err.to_string().contains("2 attempts"), "{err}");
+    }
+
+    #[test]
+    fn file_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("rover-report-{}.jsonl", std::process::id()));
+        let sink = FileSink::new(path.clone());
+        sink.push_report(&report()).unwrap();
+        sink.push_report(&report()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["problem"], "tutorial/add");
+    }
+
+    #[test]
+    fn report_options_are_parsed() {
+        assert_eq!(
+            ReportOptions::parse_header("Authorization: Bearer x:y").unwrap(),
+            ("Authorization".to_string(), "Bearer x:y".to_string())
+        );
+        assert_eq!(
+            ReportOptions::parse_field("score=1=2").unwrap(),
+            ("score".to_string(), "1=2".to_string())
+        );
+        assert!(ReportOptions::parse_header("no colon").is_err());
+    }
+
+    /// The value of the field of a multipart form.
+    fn form_field<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+        let header = format!("name=\"{name}\"\r\n\r\n");
+        let start = body.find(&header)? + header.len();
+        body[start..].split("\r\n").next()
+    }
+
+    #[test]
+    fn manytask_reports_task_of_problem() {
+        std::env::set_var("GITLAB_USER_ID", "42");
+        std::env::set_var("TESTER_TOKEN", "secret");
+        std::env::set_var("CI_COMMIT_REF_NAME", "submits/other");
+        let (url, requests) = mock_server(vec![200]);
+        Manytask::new(Some(url)).push_report(&report()).unwrap();
+        let request = requests.recv().unwrap();
+        assert_eq!(form_field(&request.body, "task"), Some("add"));
+        assert_eq!(form_field(&request.body, "user_id"), Some("42"));
+        assert_eq!(form_field(&request.body, "token"), Some("secret"));
+        assert_eq!(form_field(&request.body, "failed"), Some("1"));
+        assert_eq!(form_field(&request.body, "score"), Some("0.5000"));
+    }
+}
@@ -0,0 +1,54 @@
+use super::{
+    backoff::{check_status, Backoff},
+    Report, Reporter,
+};
+use anyhow::{Context, Result};
+use reqwest::{blocking::Client, header::CONTENT_TYPE};
+use serde_json::Value;
+
+/// Posts the report as JSON to an arbitrary URL.
+pub struct Webhook {
+    url: String,
+    headers: Vec<(String, String)>,
+    fields: Vec<(String, String)>,
+    pub(super) backoff: Backoff,
+}
+
+impl Webhook {
+    pub fn new(url: String, headers: Vec<(String, String)>, fields: Vec<(String, String)>) -> Self {
+        Self {
+            url,
+            headers,
+            fields,
+            backoff: Backoff::default(),
+        }
+    }
+
+    fn body(&self, report: &Report) -> Result<Vec<u8>> {
+        let mut body = serde_json::to_value(report).context("failed to serialize report")?;
+        if let Value::Object(object) = &mut body {
+            for (key, value) in &self.fields {
+                object.insert(key.clone(), Value::String(value.clone()));
+            }
+        }
+        serde_json::to_vec(&body).context("failed to serialize report")
+    }
+}
+
+impl Reporter for Webhook {
+    fn push_report(&self, report: &Report) -> Result<()> {
+        let body = self.body(report)?;
+        let client = Client::new();
+        let response = self.backoff.send(&self.url, || {
+            let mut request = client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            for (name, value) in &self.headers {
+                request = request.header(name, value);
+            }
+            request.send()
+        })?;
+        check_status(&self.url, response)
+    }
+}
@@ -0,0 +1,156 @@
+use super::libtest::{TestCase, TestOutcome};
+use anyhow::{Context, Result};
+use serde::{Serialize, Serializer};
+use std::{fs, path::Path, time::Duration};
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Passed,
+    Failed,
+    TimedOut,
+    Skipped,
+}
+
+impl Outcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Failed | Self::TimedOut)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommandResult {
+    pub name: String,
+    pub command: String,
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    pub outcome: Outcome,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+}
+
+impl CommandResult {
+    /// Returns the number of passed and of the run tests.
+    pub fn passed_tests(&self) -> (usize, usize) {
+        self.tests
+            .iter()
+            .fold((0, 0), |(passed, total), test| match test.outcome {
+                TestOutcome::Passed => (passed + 1, total + 1),
+                TestOutcome::Failed => (passed, total + 1),
+                TestOutcome::Ignored => (passed, total),
+            })
+    }
+
+    pub fn skipped(name: String) -> Self {
+        Self {
+            command: name.clone(),
+            name,
+            duration: Duration::ZERO,
+            outcome: Outcome::Skipped,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            tests: Vec::new(),
+        }
+    }
+
+    pub fn failed_to_launch(name: String, duration: Duration, error: &anyhow::Error) -> Self {
+        Self {
+            command: name.clone(),
+            name,
+            duration,
+            outcome: Outcome::Failed,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: format!("{error:#}\n"),
+            tests: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct StepResult {
+    pub name: String,
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    pub outcome: Outcome,
+    /// Share of the step's weight earned, from 0 to 1.
+    pub score: f64,
+    pub commands: Vec<CommandResult>,
+}
+
+impl StepResult {
+    pub fn new(name: String, commands: Vec<CommandResult>) -> Self {
+        let duration = commands.iter().map(|command| command.duration).sum();
+        let outcome = if commands.iter().any(|c| c.outcome == Outcome::TimedOut) {
+            Outcome::TimedOut
+        } else if commands.iter().any(|c| c.outcome == Outcome::Failed) {
+            Outcome::Failed
+        } else if commands.iter().all(|c| c.outcome == Outcome::Skipped) {
+            Outcome::Skipped
+        } else {
+            Outcome::Passed
+        };
+        Self {
+            name,
+            duration,
+            outcome,
+            score: 0.0,
+            commands,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TestingResult {
+    pub problem: String,
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    pub failed: bool,
+    /// Weighted score of the steps, from 0 to 1.
+    pub score: f64,
+    pub steps: Vec<StepResult>,
+}
+
+impl TestingResult {
+    pub fn new(problem: String, steps: Vec<StepResult>) -> Self {
+        let duration = steps.iter().map(|step| step.duration).sum();
+        let failed = steps.iter().any(|step| step.outcome.is_failure());
+        Self {
+            problem,
+            duration,
+            failed,
+            score: 0.0,
+            steps,
+        }
+    }
+
+    /// Lists the failed commands as "step/command".
+    pub fn failure_summary(&self) -> String {
+        self.failed_commands()
+            .map(|(step, command)| format!("{}/{}", step.name, command.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn failed_commands(&self) -> impl Iterator<Item = (&StepResult, &CommandResult)> {
+        self.steps.iter().flat_map(|step| {
+            step.commands
+                .iter()
+                .filter(|command| command.outcome.is_failure())
+                .map(move |command| (step, command))
+        })
+    }
+}
+
+pub fn write_json(value: &impl Serialize, path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(value).context("failed to serialize report")?;
+    fs::write(path, content).with_context(|| format!("failed to write report to {path:?}"))
+}
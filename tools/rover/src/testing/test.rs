@@ -1,6 +1,43 @@
-use crate::repository::problem::Problem;
+use super::result::{Outcome, TestingResult};
+use crate::repository::{context::LaunchOptions, problem::Problem};
 use anyhow::Result;
 
-pub fn test_problem(problem: Problem) -> Result<()> {
-    problem.launch_all_steps()
+pub fn outcome_mark(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Passed => "ok",
+        Outcome::Failed => "FAILED",
+        Outcome::TimedOut => "TIMED OUT",
+        Outcome::Skipped => "skipped",
+    }
+}
+
+pub fn print_summary(result: &TestingResult) {
+    println!("\nTesting summary for {}:", result.problem);
+    for step in &result.steps {
+        println!(
+            "  step {} ... {} ({:.1}s)",
+            step.name,
+            outcome_mark(step.outcome),
+            step.duration.as_secs_f64()
+        );
+        for command in &step.commands {
+            let tests = match command.passed_tests() {
+                (_, 0) => String::new(),
+                (passed, total) => format!(", {passed}/{total} tests passed"),
+            };
+            println!(
+                "    {} ... {} ({:.1}s{tests})",
+                command.name,
+                outcome_mark(command.outcome),
+                command.duration.as_secs_f64()
+            );
+        }
+    }
+    println!("  score: {:.1}%", result.score * 100.0);
+}
+
+pub fn test_problem(problem: &Problem, options: &LaunchOptions) -> Result<TestingResult> {
+    let result = problem.launch_all_steps(options)?;
+    print_summary(&result);
+    Ok(result)
 }
@@ -0,0 +1,307 @@
+use super::{
+    libtest::TestOutcome,
+    result::{Outcome, TestingResult},
+    test::outcome_mark,
+};
+use crate::repository::{context::LaunchOptions, problem::Problem};
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use notify::{Event, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long the files must stay unchanged before a rerun.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// How often Ctrl+C is checked for while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How many lines of the errors of a failed command without tests are shown.
+const OUTPUT_LINES: usize = 20;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+enum Message {
+    Changed,
+    Finished(usize, Result<TestingResult>),
+}
+
+/// The commands run in their own process groups to be cancellable, so Ctrl+C
+/// doesn't reach them: it is caught to kill the running one before exiting.
+fn catch_interrupt() {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_interrupt(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::Relaxed);
+        }
+        let handler: extern "C" fn(libc::c_int) = on_interrupt;
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+    }
+}
+
+/// The watcher may report the paths through other links than the config has them.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_solution_file(problem: &Problem, paths: &[PathBuf]) -> bool {
+    // The patterns are matched anew, so that the new files are picked up too
+    problem.config().is_ok_and(|config| {
+        let user_files: Vec<_> = config
+            .get_absolute_user_files()
+            .iter()
+            .map(|path| canonical(path))
+            .collect();
+        paths
+            .iter()
+            .any(|path| user_files.contains(&canonical(path)))
+    })
+}
+
+/// Tells the current run from the cancelled ones, whose results are dropped.
+#[derive(Default)]
+struct Generations {
+    current: usize,
+    running: bool,
+}
+
+impl Generations {
+    /// Returns the generation of the new run.
+    fn start(&mut self) -> usize {
+        self.current += 1;
+        self.running = true;
+        self.current
+    }
+
+    /// Whether the finished run is the current one.
+    fn finish(&mut self, generation: usize) -> bool {
+        let is_current = generation == self.current;
+        if is_current {
+            self.running = false;
+        }
+        is_current
+    }
+}
+
+/// Waits until no message comes for `delay`, dropping the ones that came.
+fn debounce(receiver: &Receiver<Message>, delay: Duration) -> Result<()> {
+    loop {
+        match receiver.recv_timeout(delay) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+            Err(RecvTimeoutError::Disconnected) => bail!("file watcher stopped"),
+        }
+    }
+}
+
+fn start_run(
+    problem: &Arc<Problem>,
+    options: &LaunchOptions,
+    generation: usize,
+    sender: &Sender<Message>,
+) -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = LaunchOptions {
+        quiet: true,
+        cancel: Some(cancel.clone()),
+        ..options.clone()
+    };
+    let problem = problem.clone();
+    let sender = sender.clone();
+    thread::spawn(move || {
+        let result = problem.launch_all_steps(&options);
+        // The receiver is gone only when watching is over
+        let _ = sender.send(Message::Finished(generation, result));
+    });
+    cancel
+}
+
+fn print_compact_summary(result: &Result<TestingResult>) {
+    let time = Local::now().format("%H:%M:%S");
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            println!("[{time}] error: {err:#}");
+            return;
+        }
+    };
+    println!(
+        "[{time}] {} {} ({:.1}s), score {:.1}%",
+        result.problem,
+        if result.failed { "FAILED" } else { "ok" },
+        result.duration.as_secs_f64(),
+        result.score * 100.0
+    );
+    for (step, command) in result.failed_commands() {
+        let tests = match command.passed_tests() {
+            (_, 0) => String::new(),
+            (passed, total) => format!(", {passed}/{total} tests passed"),
+        };
+        println!(
+            "  {}/{} ... {}{tests}",
+            step.name,
+            command.name,
+            outcome_mark(command.outcome)
+        );
+        let failed_tests: Vec<_> = command
+            .tests
+            .iter()
+            .filter(|test| test.outcome == TestOutcome::Failed)
+            .map(|test| test.name.as_str())
+            .collect();
+        if !failed_tests.is_empty() {
+            println!("    failed: {}", failed_tests.join(", "));
+        } else if command.outcome == Outcome::Failed {
+            // Skip the progress of cargo to the first error
+            let lines: Vec<_> = command.stderr.lines().collect();
+            let first_error = lines
+                .iter()
+                .position(|line| line.starts_with("error"))
+                .unwrap_or(0);
+            for line in lines.iter().skip(first_error).take(OUTPUT_LINES) {
+                println!("    {line}");
+            }
+        }
+    }
+}
+
+/// Reruns the steps of the problem whenever its solution files change, until Ctrl+C.
+pub fn watch_problem(problem: Problem, options: &LaunchOptions) -> Result<()> {
+    let problem = Arc::new(problem);
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher({
+        let problem = problem.clone();
+        let sender = sender.clone();
+        move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            let kind = event.kind;
+            if (kind.is_create() || kind.is_modify() || kind.is_remove())
+                && is_solution_file(&problem, &event.paths)
+            {
+                let _ = sender.send(Message::Changed);
+            }
+        }
+    })
+    .context("failed to create file watcher")?;
+    watcher
+        .watch(problem.get_path(), RecursiveMode::Recursive)
+        .with_context(|| format!("failed to watch {:?}", problem.get_path()))?;
+    catch_interrupt();
+
+    let mut generations = Generations::default();
+    let mut cancel = start_run(&problem, options, generations.start(), &sender);
+    println!("Testing {}, press Ctrl+C to stop", problem.branch_name());
+    loop {
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            cancel.store(true, Ordering::Relaxed);
+            // Wait for the running commands to be killed
+            while generations.running {
+                match receiver.recv_timeout(POLL_INTERVAL * 10) {
+                    Ok(Message::Finished(finished, _)) => {
+                        generations.finish(finished);
+                    }
+                    Ok(Message::Changed) => {}
+                    Err(_) => break,
+                }
+            }
+            return Ok(());
+        }
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Message::Finished(finished, result)) => {
+                if generations.finish(finished) {
+                    print_compact_summary(&result);
+                    println!("Watching for changes...");
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(Message::Changed) => {
+                cancel.store(true, Ordering::Relaxed);
+                debounce(&receiver, DEBOUNCE)?;
+                println!("Files changed, testing again...");
+                cancel = start_run(&problem, options, generations.start(), &sender);
+            }
+            Err(RecvTimeoutError::Disconnected) => bail!("file watcher stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::repo::Repository;
+    use std::{fs, time::Instant};
+
+    #[test]
+    fn solution_files_are_matched_canonically() {
+        let root = tempfile::tempdir().unwrap();
+        let course = root.path().join("course");
+        let problem_path = course.join("problems/tutorial/add");
+        fs::create_dir_all(problem_path.join("src")).unwrap();
+        fs::write(course.join(".rover.toml"), "").unwrap();
+        fs::write(
+            problem_path.join(".config.yml"),
+            "allowed-patterns: [src/lib.rs]\nsteps: {tests: [cargo-test]}\n",
+        )
+        .unwrap();
+        fs::write(problem_path.join("src/lib.rs"), "").unwrap();
+        fs::write(problem_path.join("README.md"), "").unwrap();
+        let repository = Repository::from_path(&course).unwrap();
+        let problem = repository.problem_from_path(&problem_path).unwrap();
+
+        let is_solution = |path: PathBuf| is_solution_file(&problem, &[path]);
+        assert!(is_solution(problem_path.join("src/lib.rs")));
+        assert!(is_solution(problem_path.join("src/../src/./lib.rs")));
+        assert!(!is_solution(problem_path.join("README.md")));
+        assert!(!is_solution(problem_path.join("src/main.rs")));
+        #[cfg(unix)]
+        {
+            let link = root.path().join("link");
+            std::os::unix::fs::symlink(&course, &link).unwrap();
+            assert!(is_solution(link.join("problems/tutorial/add/src/lib.rs")));
+        }
+    }
+
+    #[test]
+    fn only_current_run_is_reported() {
+        let mut generations = Generations::default();
+        let first = generations.start();
+        let second = generations.start();
+        assert!(!generations.finish(first));
+        assert!(generations.running);
+        assert!(generations.finish(second));
+        assert!(!generations.running);
+        let third = generations.start();
+        assert!(!generations.finish(second));
+        assert!(generations.finish(third));
+    }
+
+    #[test]
+    fn debounce_waits_for_quiet() {
+        let delay = Duration::from_millis(50);
+        let (sender, receiver) = mpsc::channel();
+        let start = Instant::now();
+        let writer = thread::spawn(move || {
+            for _ in 0..4 {
+                sender.send(Message::Changed).unwrap();
+                thread::sleep(delay / 2);
+            }
+            sender
+        });
+        debounce(&receiver, delay).unwrap();
+        assert!(start.elapsed() >= delay * 2);
+        assert!(receiver.try_recv().is_err());
+
+        drop(writer.join().unwrap());
+        assert!(debounce(&receiver, delay).is_err());
+    }
+}
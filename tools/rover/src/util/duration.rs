@@ -0,0 +1,91 @@
+use anyhow::{bail, Context, Result};
+use serde::{
+    de::{self, Visitor},
+    Deserializer,
+};
+use std::{fmt, time::Duration};
+
+/// Parses durations like `90`, `90s`, `500ms`, `5m` or `1h`; bare numbers are seconds.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("invalid duration \"{text}\""))?;
+    let seconds_per_unit: u64 = match unit.trim() {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        unit => bail!("unknown duration unit \"{unit}\" in \"{text}\""),
+    };
+    value
+        .checked_mul(seconds_per_unit)
+        .map(Duration::from_secs)
+        .with_context(|| format!("duration \"{text}\" is too long"))
+}
+
+/// A duration is written as a number of seconds or as a string for [`parse_duration`].
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DurationVisitor;
+
+    impl<'de> Visitor<'de> for DurationVisitor {
+        type Value = Option<Duration>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number of seconds or a duration like \"500ms\" or \"5m\"")
+        }
+
+        fn visit_u64<E: de::Error>(self, secs: u64) -> Result<Self::Value, E> {
+            Ok(Some(Duration::from_secs(secs)))
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+            parse_duration(text).map(Some).map_err(E::custom)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(DurationVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration(" 90s ").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("5 m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(
+            parse_duration(&format!("{}s", u64::MAX)).unwrap(),
+            Duration::from_secs(u64::MAX)
+        );
+        for text in [
+            "",
+            "s",
+            "-1s",
+            "1.5s",
+            "10d",
+            "5 minutes",
+            "99999999999999999999",
+        ] {
+            assert!(parse_duration(text).is_err(), "{text} is accepted");
+        }
+        let err = parse_duration(&format!("{}m", u64::MAX / 10)).unwrap_err();
+        assert!(err.to_string().contains("too long"), "{err}");
+        assert!(parse_duration(&format!("{}h", u64::MAX / 3600 + 1)).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        // Writing to a String never fails
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Hashes the contents of the files along with their paths relative to `root`.
+pub fn hash_files(root: &Path, relative_paths: &[PathBuf]) -> Result<String> {
+    let mut paths = relative_paths.to_vec();
+    paths.sort();
+    let mut hasher = Sha256::new();
+    for path in paths {
+        let content = fs::read(root.join(&path))
+            .with_context(|| format!("failed to read file {:?}", root.join(&path)))?;
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(content);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
@@ -1 +1,3 @@
-pub(crate) mod util_macro;
+pub(crate) mod duration;
+pub(crate) mod hash;
+pub(crate) mod size;
@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use serde::{
+    de::{self, Visitor},
+    Deserializer,
+};
+use std::fmt;
+
+/// Parses sizes like `1048576`, `512K`, `256M` or `4G` into bytes.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("invalid size \"{text}\""))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        unit => bail!("unknown size unit \"{unit}\" in \"{text}\""),
+    };
+    value
+        .checked_mul(multiplier)
+        .with_context(|| format!("size \"{text}\" is too large"))
+}
+
+/// A size is written as a number of bytes or as a string for [`parse_size`].
+pub fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct SizeVisitor;
+
+    impl<'de> Visitor<'de> for SizeVisitor {
+        type Value = Option<u64>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number of bytes or a size like \"256M\"")
+        }
+
+        fn visit_u64<E: de::Error>(self, bytes: u64) -> Result<Self::Value, E> {
+            Ok(Some(bytes))
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+            parse_size(text).map(Some).map_err(E::custom)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(SizeVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1048576").unwrap(), 1 << 20);
+        assert_eq!(parse_size("512K").unwrap(), 512 << 10);
+        assert_eq!(parse_size("256 mb").unwrap(), 256 << 20);
+        assert_eq!(parse_size("4GiB").unwrap(), 4 << 30);
+        assert_eq!(parse_size("7b").unwrap(), 7);
+        for text in ["", "M", "-1M", "1.5G", "1T", "18446744073709551616"] {
+            assert!(parse_size(text).is_err(), "{text} is accepted");
+        }
+        let err = parse_size(&format!("{}G", u64::MAX >> 29)).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+    }
+}